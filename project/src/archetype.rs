@@ -0,0 +1,201 @@
+// archetype.rs
+//
+// Tipos de enemigo definidos por datos en `levels/enemies.cfg`. Cada sección `[nombre]`
+// es un tipo y los niveles lo usan con `enemy = x y nombre [left|right]`:
+//
+//   [sentry]
+//   speed = 0                  pixeles por segundo patrullando (0 = no se mueve)
+//   chase_speed = 0            pixeles por segundo persiguiendo
+//   sight = 9                  visión en celdas
+//   fov = 90                   cono de visión en grados
+//   hearing = 1.5              multiplica el radio de los ruidos
+//   search_time = 4            segundos buscando antes de volver
+//   omniscient = false         siempre sabe dónde está el jugador
+//   alert = 8                  al ver al jugador avisa a los que estén a N celdas de pasillo
+//   teleport = 0               segundos entre teletransportes cerca del jugador (0 = nunca)
+//   frames = d e               llaves de textura de la animación
+//   frame_time = 0.4           segundos por cuadro
+//   sound = assets/x.mp3       se reproduce cuando empieza a perseguir (opcional)
+//
+// Las claves que falten toman los valores de `reggie`, el enemigo original.
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use crate::enemy::{DEFAULT_FOV, DEFAULT_HEARING, DEFAULT_SEARCH_TIME, DEFAULT_SIGHT, EnemyParams, TurnPreference};
+use crate::level::{LevelError, first_column, tokens};
+
+pub const ARCHETYPES_FILE: &str = "enemies.cfg";
+pub const DEFAULT_ARCHETYPE: &str = "reggie";
+pub const DEFAULT_SPEED: f32 = 400.0; // pixeles por segundo de `reggie`, base de todos los tipos
+
+#[derive(Clone, Debug)]
+pub struct Archetype {
+    pub name: String,
+    pub params: EnemyParams,
+    pub frames: Vec<char>,
+    pub frame_time: f32,
+    pub sound: Option<String>,
+}
+
+impl Archetype {
+    // El Reggie Bot de siempre: sigue paredes, alterna entre 'e' y 'f'
+    pub fn reggie(speed: f32) -> Self {
+        Archetype {
+            name: DEFAULT_ARCHETYPE.to_string(),
+            params: EnemyParams {
+                speed,
+                chase_speed: speed,
+                sight: DEFAULT_SIGHT,
+                fov: DEFAULT_FOV,
+                hearing: DEFAULT_HEARING,
+                search_time: DEFAULT_SEARCH_TIME,
+                turn_preference: TurnPreference::Right,
+                omniscient: false,
+                alert_radius: 0.0,
+                teleport_interval: 0.0,
+            },
+            frames: vec!['e', 'f'],
+            frame_time: 0.4,
+            sound: None,
+        }
+    }
+}
+
+pub fn load_archetypes(path: impl AsRef<Path>) -> Result<HashMap<String, Archetype>, LevelError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| LevelError::io(path, e))?;
+    parse_archetypes(&source, &path.display().to_string())
+}
+
+pub fn parse_archetypes(source: &str, path: &str) -> Result<HashMap<String, Archetype>, LevelError> {
+    let mut archetypes = HashMap::new();
+    archetypes.insert(DEFAULT_ARCHETYPE.to_string(), Archetype::reggie(DEFAULT_SPEED));
+    let mut current: Option<Archetype> = None;
+    let mut seen = HashSet::new(); // secciones de este archivo, para avisar de las repetidas
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |column: usize, message: String| LevelError::Parse { path: path.to_string(), line: index + 1, column, message };
+        let start = first_column(line);
+
+        if let Some(name) = line.trim().strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            let name = name.trim();
+            // Un nombre como `inf` o `3` se confundiría con un número en el formato de enemigos
+            if name.is_empty() || name.contains(char::is_whitespace) || name.parse::<f32>().is_ok() {
+                return Err(error(start, format!("`{}` no sirve como nombre de tipo", name)));
+            }
+            if !seen.insert(name.to_string()) {
+                return Err(error(start, format!("la sección [{}] está repetida", name)));
+            }
+            if let Some(done) = current.take() {
+                archetypes.insert(done.name.clone(), done);
+            }
+            let mut archetype = Archetype::reggie(DEFAULT_SPEED);
+            archetype.name = name.to_string();
+            current = Some(archetype);
+            continue;
+        }
+
+        let Some(equals) = line.find('=') else {
+            return Err(error(start, "se esperaba `[nombre]` o `clave = valor`".to_string()));
+        };
+        let key = line[..equals].trim();
+        let values = tokens(line, equals + 1);
+        let value = line[equals + 1..].trim();
+        let value_column = values.first().map_or(line.len() + 1, |&(column, _)| column);
+        let Some(archetype) = current.as_mut() else {
+            return Err(error(start, format!("`{}` antes de la primera sección [nombre]", key)));
+        };
+        let number = || value.parse::<f32>().map_err(|_| error(value_column, format!("`{}` no es un número", value)));
+        let params = &mut archetype.params;
+        match key {
+            "speed" => params.speed = number()?,
+            "chase_speed" => params.chase_speed = number()?,
+            "sight" => params.sight = number()?,
+            "fov" => params.fov = number()?.to_radians(),
+            "hearing" => params.hearing = number()?,
+            "search_time" => params.search_time = number()?,
+            "alert" => params.alert_radius = number()?,
+            "teleport" => params.teleport_interval = number()?,
+            "omniscient" => params.omniscient = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(error(value_column, format!("`{}` no es true/false", value))),
+            },
+            "frames" => {
+                let mut frames = Vec::new();
+                for &(column, frame) in &values {
+                    let mut chars = frame.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => frames.push(c),
+                        _ => return Err(error(column, format!("la textura `{}` debe ser un solo caracter", frame))),
+                    }
+                }
+                if frames.is_empty() {
+                    return Err(error(value_column, "`frames` necesita al menos una textura".to_string()));
+                }
+                archetype.frames = frames;
+            }
+            "frame_time" => archetype.frame_time = number()?,
+            "sound" => archetype.sound = Some(value.to_string()),
+            _ => return Err(error(start, format!("clave `{}` desconocida", key))),
+        }
+    }
+    if let Some(done) = current.take() {
+        archetypes.insert(done.name.clone(), done);
+    }
+    Ok(archetypes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column_of(result: Result<HashMap<String, Archetype>, LevelError>) -> (usize, usize) {
+        match result {
+            Err(LevelError::Parse { line, column, .. }) => (line, column),
+            Err(e) => panic!("error inesperado: {}", e),
+            Ok(_) => panic!("se esperaba un error"),
+        }
+    }
+
+    #[test]
+    fn shipped_archetypes_parse() {
+        let archetypes = load_archetypes("levels/enemies.cfg").unwrap_or_else(|e| panic!("{}", e));
+        for name in [DEFAULT_ARCHETYPE, "tracker", "patroller", "sentry", "ambusher"] {
+            assert!(archetypes.contains_key(name), "falta `{}`", name);
+        }
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        assert_eq!(column_of(parse_archetypes("[a]\n  colour = red", "t")), (2, 3));
+    }
+
+    #[test]
+    fn bad_frames_are_rejected() {
+        assert_eq!(column_of(parse_archetypes("[a]\nframes = d ee", "t")), (2, 12));
+        assert_eq!(column_of(parse_archetypes("[a]\nframes =", "t")), (2, 9));
+    }
+
+    #[test]
+    fn bad_numbers_report_their_column() {
+        assert_eq!(column_of(parse_archetypes("[a]\nspeed = fast", "t")), (2, 9));
+    }
+
+    #[test]
+    fn repeated_section_is_rejected() {
+        assert_eq!(column_of(parse_archetypes("[a]\nspeed = 1\n[a]", "t")), (3, 1));
+    }
+
+    #[test]
+    fn numeric_names_are_rejected() {
+        for name in ["inf", "nan", "3", "-1.5"] {
+            let source = format!("[{}]", name);
+            assert_eq!(column_of(parse_archetypes(&source, "t")), (1, 1));
+        }
+    }
+}
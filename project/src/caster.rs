@@ -3,50 +3,122 @@ use raylib::prelude::*;
 use crate::player::Player;
 use crate::maze::Maze;
 use crate::framebuffer::Framebuffer;
-use crate::textures::TextureManager;
 
-pub struct Intersect {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side { //Cara de la pared que golpeó el rayo
+    North,
+    South,
+    East,
+    West,
+}
+
+pub struct Intersect { //Impacto del rayo: sirve para dibujar las paredes y para la visibilidad
     pub distance: f32,
     pub impact: char,
-    pub tx: usize,
+    pub side: Side,
+    pub hit: Vector2, // punto exacto del impacto en pixeles del mundo
+    pub wall_x: f32, // coordenada de textura exacta en [0, 1)
 }
 
-pub fn cast_ray_intersect(
-    maze: &Maze,
-    player: &Player,
-    a: f32,
-    block_size: usize,
-    texture_manager: &TextureManager, // Added TextureManager
-) -> Intersect {
-    let mut d = 0.0;
+#[derive(Clone, Copy)]
+pub struct RayLimits { //Límites del rayo para niveles abiertos o con filas irregulares
+    pub max_distance: f32, // distancia máxima en pixeles antes de considerar que el rayo falló
+    pub void_tile: Option<char>, // si hay valor, salir de la cuadricula cuenta como impacto con esta textura
+}
+
+impl Default for RayLimits {
+    fn default() -> Self {
+        RayLimits { max_distance: 1000.0, void_tile: None }
+    }
+}
+
+// Recorre la cuadricula desde `origin` en el ángulo `a` hasta la primera pared
+pub fn cast_grid(maze: &Maze, origin: Vector2, a: f32, block_size: usize, limits: &RayLimits) -> Option<Intersect> {
+    cast_dir(maze, origin, Vector2::new(a.cos(), a.sin()), block_size, limits)
+}
+
+// Igual que `cast_grid` pero con una dirección de cualquier largo en vez de un ángulo
+pub fn cast_dir(maze: &Maze, origin: Vector2, dir: Vector2, block_size: usize, limits: &RayLimits) -> Option<Intersect> {
+    // Una dirección nula o con NaN no apunta a ningún lado: el DDA nunca avanzaría
+    let length = dir.length();
+    if !(length.is_finite() && length > 0.0) {
+        return None;
+    }
+    // DDA: recorremos la cuadricula celda por celda en vez de avanzar pixel por pixel
+    let bs = block_size as f32;
+    let dir_x = dir.x / length;
+    let dir_y = dir.y / length;
+
+    // Posición en unidades de celda
+    let pos_x = origin.x / bs;
+    let pos_y = origin.y / bs;
+    let mut i = pos_x.floor() as i32;
+    let mut j = pos_y.floor() as i32;
+
+    // Distancia (en celdas) que recorre el rayo para cruzar una celda completa en cada eje
+    let delta_x = if dir_x == 0.0 { f32::INFINITY } else { (1.0 / dir_x).abs() };
+    let delta_y = if dir_y == 0.0 { f32::INFINITY } else { (1.0 / dir_y).abs() };
+
+    let (step_x, mut side_x) = if dir_x < 0.0 {
+        (-1, (pos_x - i as f32) * delta_x)
+    } else {
+        (1, (i as f32 + 1.0 - pos_x) * delta_x)
+    };
+    let (step_y, mut side_y) = if dir_y < 0.0 {
+        (-1, (pos_y - j as f32) * delta_y)
+    } else {
+        (1, (j as f32 + 1.0 - pos_y) * delta_y)
+    };
+
     loop {
-        let cos = d * a.cos();
-        let sin = d * a.sin();
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
-
-        let i = x / block_size;
-        let j = y / block_size;
-        
-        if maze[j][i] != ' ' {
-            let hitx = x - i * block_size;
-            let hity = y - j * block_size;
-            let mut maxhit = hity;
-
-            if 1 < hitx && hitx < block_size - 1 {
-                maxhit = hitx;
+        let side = if side_x < side_y {
+            i += step_x;
+            side_x += delta_x;
+            if step_x > 0 { Side::West } else { Side::East }
+        } else {
+            j += step_y;
+            side_y += delta_y;
+            if step_y > 0 { Side::North } else { Side::South }
+        };
+
+        let cells = match side {
+            Side::West | Side::East => side_x - delta_x,
+            Side::North | Side::South => side_y - delta_y,
+        };
+        let distance = cells * bs;
+        if distance > limits.max_distance {
+            return None;
+        }
+
+        // Fuera de la cuadricula: o es el "vacío" configurado o el rayo se pierde
+        let cell = match maze.get(i, j) {
+            Some(tile) => tile.texture_key(),
+            None => match limits.void_tile {
+                Some(void) => void,
+                None => return None,
+            },
+        };
+        if cell != ' ' {
+            let hit = Vector2::new(origin.x + dir_x * distance, origin.y + dir_y * distance);
+
+            // Posición del impacto a lo largo de la pared, invertida en las caras opuestas
+            // para que la textura no salga en espejo
+            let along = match side {
+                Side::West | Side::East => hit.y / bs,
+                Side::North | Side::South => hit.x / bs,
+            };
+            let mut wall_x = along - along.floor();
+            if side == Side::East || side == Side::North {
+                wall_x = 1.0 - wall_x;
             }
-            
-            let (tex_width, _) = texture_manager.get_image_dimensions(maze[j][i]).unwrap_or((128, 128)); // Get actual width
-            let x = ((maxhit as f32 * tex_width as f32) / block_size as f32) as usize; // Use actual width
-            return Intersect {
-                distance: d,
-                impact: maze[j][i],
-                tx: x,
-            }; 
+            return Some(Intersect {
+                distance,
+                impact: cell,
+                side,
+                hit,
+                wall_x,
+            });
         }
-        
-        d += 1.0;
     }
 }
 
@@ -57,13 +129,15 @@ pub fn cast_ray(
     a: f32,
     block_size: usize,
     draw: bool,
-    texture_manager: &TextureManager, // Added TextureManager
-) -> Intersect {
-    let intersect = cast_ray_intersect(maze, player, a, block_size, texture_manager);
+    limits: &RayLimits,
+) -> Option<Intersect> {
+    let intersect = cast_grid(maze, player.pos, a, block_size, limits);
 
     if draw {
+        // Si el rayo no pegó en nada se dibuja hasta la distancia máxima
+        let length = intersect.as_ref().map_or(limits.max_distance, |hit| hit.distance);
         let mut d = 0.0;
-        while d < intersect.distance {
+        while d < length {
             let cos = d * a.cos();
             let sin = d * a.sin();
             let x = (player.pos.x + cos) as i32;
@@ -72,6 +146,116 @@ pub fn cast_ray(
             d += 1.0;
         }
     }
-    
+
     intersect
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{RaggedRows, parse_maze};
+    use std::f32::consts::PI;
+
+    const EPSILON: f32 = 1e-2;
+
+    fn room() -> Maze {
+        // 8 celdas abiertas de ancho y 3 de alto; las paredes en x = 100 y 900, y = 100 y 400
+        parse_maze("+--+--+--+\n|        |\n|        |\n|        |\n+--+--+--+", RaggedRows::Reject).0
+    }
+
+    fn cast(origin: (f32, f32), a: f32) -> Intersect {
+        cast_grid(&room(), Vector2::new(origin.0, origin.1), a, 100, &RayLimits::default()).expect("el rayo debe pegar")
+    }
+
+    fn assert_close(actual: f32, expected: f32, what: &str) {
+        assert!((actual - expected).abs() < EPSILON, "{}: {} en vez de {}", what, actual, expected);
+    }
+
+    #[test]
+    fn axis_aligned_rays_hit_each_side() {
+        // (ángulo, distancia, cara, wall_x) desde (130, 170): las caras este y norte se invierten
+        let cases = [
+            (0.0, 770.0, Side::West, 0.7),
+            (PI, 30.0, Side::East, 0.3),
+            (-PI / 2.0, 70.0, Side::South, 0.3),
+            (PI / 2.0, 230.0, Side::North, 0.7),
+        ];
+        for (a, distance, side, wall_x) in cases {
+            let hit = cast((130.0, 170.0), a);
+            assert_eq!(hit.side, side, "ángulo {}", a);
+            assert_close(hit.distance, distance, "distancia");
+            assert_close(hit.wall_x, wall_x, "wall_x");
+        }
+    }
+
+    #[test]
+    fn slanted_ray_has_exact_perpendicular_distance() {
+        let a = 0.1;
+        let hit = cast((150.0, 250.0), a);
+        assert_eq!(hit.side, Side::West);
+        assert_close(hit.distance * a.cos(), 750.0, "distancia perpendicular");
+        assert_close(hit.hit.x, 900.0, "x del impacto");
+        assert_close(hit.wall_x, (250.0 + 750.0 * a.tan()) / 100.0 % 1.0, "wall_x");
+    }
+
+    #[test]
+    fn diagonal_ray_crosses_the_nearer_border_first() {
+        // Desde (130, 350) a 45°: la pared de abajo (y = 400) está a 50, la de la derecha a 770
+        let hit = cast((130.0, 350.0), PI / 4.0);
+        assert_eq!(hit.side, Side::North);
+        assert_close(hit.distance, 50.0 * 2f32.sqrt(), "distancia");
+        assert_close(hit.hit.x, 180.0, "x del impacto");
+        assert_close(hit.wall_x, 0.2, "wall_x");
+    }
+
+    // Fila abierta que llega hasta el borde derecho: el rayo puede salir de la cuadricula
+    fn open_row() -> Maze {
+        parse_maze("+--+\n|   \n+--+", RaggedRows::Reject).0
+    }
+
+    #[test]
+    fn ray_leaving_the_grid_is_lost() {
+        let maze = open_row();
+        assert!(cast_grid(&maze, Vector2::new(150.0, 150.0), 0.0, 100, &RayLimits::default()).is_none());
+        // Con `void_tile` el borde de la cuadricula cuenta como pared
+        let limits = RayLimits { void_tile: Some('#'), ..RayLimits::default() };
+        let hit = cast_grid(&maze, Vector2::new(150.0, 150.0), 0.0, 100, &limits).expect("el vacío cuenta como pared");
+        assert_eq!(hit.impact, '#');
+        assert_close(hit.distance, 250.0, "distancia al vacío");
+    }
+
+    #[test]
+    fn ray_through_void_stops_at_max_distance() {
+        let maze = open_row();
+        let short = RayLimits { max_distance: 200.0, void_tile: Some('#') };
+        assert!(cast_grid(&maze, Vector2::new(150.0, 150.0), 0.0, 100, &short).is_none());
+        // Más lejos que cualquier pared del laberinto también se corta
+        let far = RayLimits { max_distance: 50.0, void_tile: None };
+        assert!(cast_grid(&room(), Vector2::new(500.0, 250.0), 0.0, 100, &far).is_none());
+    }
+
+    #[test]
+    fn nan_and_zero_directions_return_none() {
+        let (maze, limits) = (room(), RayLimits { void_tile: Some('#'), ..RayLimits::default() });
+        let origin = Vector2::new(150.0, 150.0);
+        assert!(cast_grid(&maze, origin, f32::NAN, 100, &limits).is_none());
+        assert!(cast_grid(&maze, origin, f32::INFINITY, 100, &limits).is_none());
+        assert!(cast_dir(&maze, origin, Vector2::zero(), 100, &limits).is_none());
+        assert!(cast_dir(&maze, origin, Vector2::new(f32::NAN, 1.0), 100, &limits).is_none());
+        // Cualquier largo sirve mientras no sea cero
+        assert_close(cast_dir(&maze, origin, Vector2::new(0.001, 0.0), 100, &limits).unwrap().distance, 750.0, "dirección corta");
+    }
+
+    #[test]
+    fn rays_starting_on_a_cell_border() {
+        // Justo en el borde entre dos celdas abiertas, en las dos direcciones
+        assert_close(cast((200.0, 150.0), 0.0).distance, 700.0, "hacia el este");
+        assert_close(cast((200.0, 150.0), PI).distance, 100.0, "hacia el oeste");
+        assert_close(cast((250.0, 200.0), PI / 2.0).distance, 200.0, "hacia el sur");
+        assert_close(cast((250.0, 200.0), -PI / 2.0).distance, 100.0, "hacia el norte");
+        // Pegado a una pared: la toca a distancia 0
+        let touching = cast((100.0, 150.0), PI);
+        assert_eq!(touching.side, Side::East);
+        assert_close(touching.distance, 0.0, "pegado a la pared");
+    }
+}
@@ -14,4 +14,8 @@ impl Collectable {
             texture_key,
         }
     }
-}
\ No newline at end of file
+}
+// Solo las consolas Wii ('c', 'h', 'f') suman puntos; el resto es decoración
+pub fn awards_point(texture_key: char) -> bool {
+    matches!(texture_key, 'f' | 'c' | 'h')
+}
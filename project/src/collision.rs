@@ -0,0 +1,161 @@
+// collision.rs
+//
+// Colisión de un círculo contra la cuadricula del laberinto. El movimiento se resuelve
+// primero en X y luego en Y, así al chocar en diagonal se sigue deslizando por la pared.
+// La usan el jugador y los enemigos.
+use raylib::prelude::*;
+use crate::maze::{Maze, Tile};
+
+pub const PLAYER_RADIUS: f32 = 20.0;
+pub const ENEMY_RADIUS: f32 = 20.0;
+const CONTACT_EPSILON: f32 = 1.0; // margen para detectar que se está tocando una celda
+const MAX_SUBSTEPS: f32 = 64.0; // tope de pasos por movimiento, por si llega un delta absurdo
+
+pub struct MoveResult {
+    pub pos: Vector2,
+    pub blocked_x: bool,
+    pub blocked_y: bool,
+    pub touched_exit: bool, // el círculo quedó pegado a una salida 'g'
+}
+
+impl MoveResult {
+    pub fn blocked(&self) -> bool {
+        self.blocked_x || self.blocked_y
+    }
+}
+
+// Fuera de la cuadricula también cuenta como pared
+fn is_solid(tile: Option<Tile>) -> bool {
+    !tile.is_some_and(Tile::is_open)
+}
+
+// Celdas que puede tocar un círculo en `pos` con radio `radius`
+fn nearby_cells(pos: Vector2, radius: f32, bs: f32) -> impl Iterator<Item = (i32, i32)> {
+    let min_i = ((pos.x - radius) / bs).floor() as i32;
+    let max_i = ((pos.x + radius) / bs).floor() as i32;
+    let min_j = ((pos.y - radius) / bs).floor() as i32;
+    let max_j = ((pos.y + radius) / bs).floor() as i32;
+    (min_j..=max_j).flat_map(move |j| (min_i..=max_i).map(move |i| (i, j)))
+}
+
+// Empuja el círculo fuera de las celdas sólidas; `axis_dir` es el movimiento en el eje que
+// se está resolviendo, por si el centro quedó dentro de una pared
+fn resolve(maze: &Maze, pos: &mut Vector2, radius: f32, bs: f32, axis_dir: Vector2) -> bool {
+    let mut hit = false;
+    for (i, j) in nearby_cells(*pos, radius, bs) {
+        if !is_solid(maze.get(i, j)) { continue; }
+        let (left, top) = (i as f32 * bs, j as f32 * bs);
+        let closest = Vector2::new(pos.x.clamp(left, left + bs), pos.y.clamp(top, top + bs));
+        let d = *pos - closest;
+        let dist2 = d.x * d.x + d.y * d.y;
+        if dist2 >= radius * radius { continue; }
+        hit = true;
+        if dist2 > 1e-6 {
+            let dist = dist2.sqrt();
+            *pos += d * ((radius - dist) / dist);
+        } else if axis_dir.x > 0.0 {
+            pos.x = left - radius;
+        } else if axis_dir.x < 0.0 {
+            pos.x = left + bs + radius;
+        } else if axis_dir.y > 0.0 {
+            pos.y = top - radius;
+        } else {
+            pos.y = top + bs + radius;
+        }
+    }
+    hit
+}
+
+pub fn move_circle(maze: &Maze, pos: Vector2, delta: Vector2, radius: f32, block_size: usize) -> MoveResult {
+    let bs = block_size as f32;
+    let mut next = pos;
+
+    // Se avanza de a lo más un radio por paso: con un dt grande el círculo no puede saltarse
+    // una pared completa sin que su centro quede dentro de ella
+    let steps = (delta.x.abs().max(delta.y.abs()) / radius).ceil().clamp(1.0, MAX_SUBSTEPS) as usize;
+    let step = delta / steps as f32;
+    let (mut blocked_x, mut blocked_y) = (false, false);
+    for _ in 0..steps {
+        next.x += step.x;
+        blocked_x |= resolve(maze, &mut next, radius, bs, Vector2::new(step.x, 0.0)) && step.x != 0.0;
+        next.y += step.y;
+        blocked_y |= resolve(maze, &mut next, radius, bs, Vector2::new(0.0, step.y)) && step.y != 0.0;
+    }
+
+    let touched_exit = nearby_cells(next, radius + CONTACT_EPSILON, bs).any(|(i, j)| {
+        if maze.get(i, j) != Some(Tile::Exit) { return false; }
+        let (left, top) = (i as f32 * bs, j as f32 * bs);
+        let closest = Vector2::new(next.x.clamp(left, left + bs), next.y.clamp(top, top + bs));
+        next.distance_to(closest) <= radius + CONTACT_EPSILON
+    });
+
+    MoveResult { pos: next, blocked_x, blocked_y, touched_exit }
+}
+
+// ¿Se puede avanzar `delta` desde `pos` sin chocar?
+pub fn is_clear(maze: &Maze, pos: Vector2, delta: Vector2, radius: f32, block_size: usize) -> bool {
+    !move_circle(maze, pos, delta, radius, block_size).blocked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{RaggedRows, parse_maze};
+
+    const RADIUS: f32 = 20.0;
+
+    fn maze(source: &str) -> Maze {
+        parse_maze(source, RaggedRows::Reject).0
+    }
+
+    // Cuarto de 8x3 celdas con un pilar en la celda (4, 2)
+    fn room() -> Maze {
+        maze("+--+--+--+\n|        |\n|   +    |\n|        |\n+--+--+--+")
+    }
+
+    fn assert_close(actual: Vector2, expected: (f32, f32)) {
+        assert!((actual.x - expected.0).abs() < 1e-3 && (actual.y - expected.1).abs() < 1e-3, "{:?} en vez de {:?}", (actual.x, actual.y), expected);
+    }
+
+    #[test]
+    fn diagonal_push_slides_along_the_wall() {
+        // Pegado a la pared de arriba (y = 100) y empujando hacia arriba a la derecha
+        let result = move_circle(&room(), Vector2::new(150.0, 125.0), Vector2::new(10.0, -10.0), RADIUS, 100);
+        assert_close(result.pos, (160.0, 120.0));
+        assert!(result.blocked_y && !result.blocked_x);
+    }
+
+    #[test]
+    fn inside_corner_blocks_both_axes() {
+        let result = move_circle(&room(), Vector2::new(130.0, 130.0), Vector2::new(-20.0, -20.0), RADIUS, 100);
+        assert_close(result.pos, (120.0, 120.0));
+        assert!(result.blocked_x && result.blocked_y);
+    }
+
+    #[test]
+    fn outside_corner_pushes_away_from_the_vertex() {
+        // La esquina de arriba a la izquierda del pilar está en (400, 200)
+        let corner = Vector2::new(400.0, 200.0);
+        let result = move_circle(&room(), Vector2::new(380.0, 180.0), Vector2::new(8.0, 8.0), RADIUS, 100);
+        assert!(result.blocked());
+        assert!((result.pos.distance_to(corner) - RADIUS).abs() < 1e-3, "{:?}", (result.pos.x, result.pos.y));
+        assert!(result.pos.x < corner.x && result.pos.y < corner.y);
+    }
+
+    #[test]
+    fn large_step_does_not_tunnel_through_a_wall() {
+        // Pared de una celda en x = 300..400 entre dos pasillos abiertos
+        let corridor = maze("+--+--+\n|  |  |\n+--+--+");
+        let result = move_circle(&corridor, Vector2::new(250.0, 150.0), Vector2::new(300.0, 0.0), RADIUS, 100);
+        assert_close(result.pos, (280.0, 150.0));
+        assert!(result.blocked_x);
+        assert!(!is_clear(&corridor, Vector2::new(250.0, 150.0), Vector2::new(1000.0, 0.0), RADIUS, 100));
+    }
+
+    #[test]
+    fn free_move_is_not_blocked() {
+        let result = move_circle(&room(), Vector2::new(150.0, 150.0), Vector2::new(30.0, 50.0), RADIUS, 100);
+        assert_close(result.pos, (180.0, 200.0));
+        assert!(!result.blocked() && !result.touched_exit);
+    }
+}
@@ -0,0 +1,673 @@
+// controls.rs
+//
+// Capa de acciones: el juego pregunta por `Action::MoveForward` y no por `KEY_W`. Los
+// controles se guardan en `controls.cfg` con una línea por acción:
+//
+//   move_forward = W, UP, MOUSE_LEFT, LSTICK_UP
+//   mouse_sensitivity = 0.002
+//   invert_mouse = false
+//   gamepad_look_speed = 3.0      radianes por segundo con el stick derecho a fondo
+//   gamepad_dead_zone = 0.2       inclinación mínima de los sticks y gatillos
+//
+// Los botones del control empiezan con `PAD_` y las direcciones de los sticks y los
+// gatillos son `LSTICK_UP`, `RSTICK_LEFT`, `LT`, `RT`, ... Se usa el primer control conectado.
+// Al moverse los sticks son analógicos; en los menús un eje cuenta como una pulsación al
+// salir de la zona muerta y se repite si se mantiene inclinado.
+//
+// Las acciones que falten en el archivo se quedan con las teclas por defecto.
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const CONTROLS_FILE: &str = "controls.cfg";
+pub const DEFAULT_MOUSE_SENSITIVITY: f32 = 0.002; // radianes por pixel de mouse
+pub const DEFAULT_GAMEPAD_LOOK_SPEED: f32 = 3.0; // radianes por segundo
+pub const DEFAULT_GAMEPAD_DEAD_ZONE: f32 = 0.2;
+const GAMEPAD: i32 = 0;
+const CANCEL_KEY: KeyboardKey = KeyboardKey::KEY_BACKSPACE; // cancela la espera de una tecla nueva
+const CAPTURE_TIMEOUT: f32 = 5.0; // segundos; sin teclado también se sale de la espera
+const MENU_REPEAT_DELAY: f32 = 0.4; // segundos con un eje inclinado antes de que se repita
+const MENU_REPEAT_RATE: f32 = 0.1; // segundos entre repeticiones después de la espera
+// Sin una tecla en estas acciones un jugador de teclado no puede salir del menú ni volver a
+// la pantalla de controles
+const ESSENTIAL: [Action; 2] = [Action::Confirm, Action::Back];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Sprint,
+    Crouch,
+    ToggleMap,
+    Back,
+    Confirm,
+    MenuUp,
+    MenuDown,
+    DebugOverlay,
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Sprint,
+        Action::Crouch,
+        Action::ToggleMap,
+        Action::Back,
+        Action::Confirm,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::DebugOverlay,
+    ];
+
+    // Nombre en el archivo de configuración
+    pub fn key(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Sprint => "sprint",
+            Action::Crouch => "crouch",
+            Action::ToggleMap => "toggle_map",
+            Action::Back => "back",
+            Action::Confirm => "confirm",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::DebugOverlay => "debug_overlay",
+        }
+    }
+
+    // Nombre en la pantalla de controles
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::Sprint => "Sprint",
+            Action::Crouch => "Crouch",
+            Action::ToggleMap => "Show map (hold)",
+            Action::Back => "Back to menu",
+            Action::Confirm => "Confirm",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::DebugOverlay => "AI debug overlay",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.key() == key)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyboardKey),
+    Mouse(MouseButton),
+    Pad(GamepadButton),
+    Axis(GamepadAxis, i8), // eje y dirección (+1 / -1) en la que cuenta como presionado
+}
+
+// Teclas que se pueden asignar, con su nombre en el archivo
+const KEY_NAMES: &[(KeyboardKey, &str)] = &[
+    (KeyboardKey::KEY_A, "A"), (KeyboardKey::KEY_B, "B"), (KeyboardKey::KEY_C, "C"), (KeyboardKey::KEY_D, "D"),
+    (KeyboardKey::KEY_E, "E"), (KeyboardKey::KEY_F, "F"), (KeyboardKey::KEY_G, "G"), (KeyboardKey::KEY_H, "H"),
+    (KeyboardKey::KEY_I, "I"), (KeyboardKey::KEY_J, "J"), (KeyboardKey::KEY_K, "K"), (KeyboardKey::KEY_L, "L"),
+    (KeyboardKey::KEY_M, "M"), (KeyboardKey::KEY_N, "N"), (KeyboardKey::KEY_O, "O"), (KeyboardKey::KEY_P, "P"),
+    (KeyboardKey::KEY_Q, "Q"), (KeyboardKey::KEY_R, "R"), (KeyboardKey::KEY_S, "S"), (KeyboardKey::KEY_T, "T"),
+    (KeyboardKey::KEY_U, "U"), (KeyboardKey::KEY_V, "V"), (KeyboardKey::KEY_W, "W"), (KeyboardKey::KEY_X, "X"),
+    (KeyboardKey::KEY_Y, "Y"), (KeyboardKey::KEY_Z, "Z"),
+    (KeyboardKey::KEY_ZERO, "0"), (KeyboardKey::KEY_ONE, "1"), (KeyboardKey::KEY_TWO, "2"), (KeyboardKey::KEY_THREE, "3"),
+    (KeyboardKey::KEY_FOUR, "4"), (KeyboardKey::KEY_FIVE, "5"), (KeyboardKey::KEY_SIX, "6"), (KeyboardKey::KEY_SEVEN, "7"),
+    (KeyboardKey::KEY_EIGHT, "8"), (KeyboardKey::KEY_NINE, "9"),
+    (KeyboardKey::KEY_UP, "UP"), (KeyboardKey::KEY_DOWN, "DOWN"), (KeyboardKey::KEY_LEFT, "LEFT"), (KeyboardKey::KEY_RIGHT, "RIGHT"),
+    (KeyboardKey::KEY_SPACE, "SPACE"), (KeyboardKey::KEY_ENTER, "ENTER"), (KeyboardKey::KEY_TAB, "TAB"),
+    (KeyboardKey::KEY_BACKSPACE, "BACKSPACE"),
+    (KeyboardKey::KEY_LEFT_SHIFT, "LEFT_SHIFT"), (KeyboardKey::KEY_RIGHT_SHIFT, "RIGHT_SHIFT"),
+    (KeyboardKey::KEY_LEFT_CONTROL, "LEFT_CONTROL"), (KeyboardKey::KEY_RIGHT_CONTROL, "RIGHT_CONTROL"),
+    (KeyboardKey::KEY_LEFT_ALT, "LEFT_ALT"), (KeyboardKey::KEY_RIGHT_ALT, "RIGHT_ALT"),
+    (KeyboardKey::KEY_F1, "F1"), (KeyboardKey::KEY_F2, "F2"), (KeyboardKey::KEY_F3, "F3"), (KeyboardKey::KEY_F4, "F4"),
+];
+
+const MOUSE_NAMES: &[(MouseButton, &str)] = &[
+    (MouseButton::MOUSE_BUTTON_LEFT, "MOUSE_LEFT"),
+    (MouseButton::MOUSE_BUTTON_RIGHT, "MOUSE_RIGHT"),
+    (MouseButton::MOUSE_BUTTON_MIDDLE, "MOUSE_MIDDLE"),
+];
+
+const PAD_NAMES: &[(GamepadButton, &str)] = &[
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN, "PAD_A"),
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT, "PAD_B"),
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT, "PAD_X"),
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP, "PAD_Y"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP, "PAD_UP"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN, "PAD_DOWN"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT, "PAD_LEFT"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT, "PAD_RIGHT"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1, "PAD_LB"),
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1, "PAD_RB"),
+    (GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT, "PAD_SELECT"),
+    (GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT, "PAD_START"),
+    (GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB, "PAD_LSTICK"),
+    (GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB, "PAD_RSTICK"),
+];
+
+// En raylib el eje Y de los sticks crece hacia abajo y los gatillos van de -1 (suelto) a 1
+const AXIS_NAMES: &[(GamepadAxis, i8, &str)] = &[
+    (GamepadAxis::GAMEPAD_AXIS_LEFT_Y, -1, "LSTICK_UP"),
+    (GamepadAxis::GAMEPAD_AXIS_LEFT_Y, 1, "LSTICK_DOWN"),
+    (GamepadAxis::GAMEPAD_AXIS_LEFT_X, -1, "LSTICK_LEFT"),
+    (GamepadAxis::GAMEPAD_AXIS_LEFT_X, 1, "LSTICK_RIGHT"),
+    (GamepadAxis::GAMEPAD_AXIS_RIGHT_Y, -1, "RSTICK_UP"),
+    (GamepadAxis::GAMEPAD_AXIS_RIGHT_Y, 1, "RSTICK_DOWN"),
+    (GamepadAxis::GAMEPAD_AXIS_RIGHT_X, -1, "RSTICK_LEFT"),
+    (GamepadAxis::GAMEPAD_AXIS_RIGHT_X, 1, "RSTICK_RIGHT"),
+    (GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER, 1, "LT"),
+    (GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER, 1, "RT"),
+];
+
+impl Binding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Binding::Key(key) => KEY_NAMES.iter().find(|(k, _)| *k == key).map_or("?", |(_, name)| name),
+            Binding::Mouse(button) => MOUSE_NAMES.iter().find(|(b, _)| *b == button).map_or("?", |(_, name)| name),
+            Binding::Pad(button) => PAD_NAMES.iter().find(|(b, _)| *b == button).map_or("?", |(_, name)| name),
+            Binding::Axis(axis, sign) => AXIS_NAMES.iter().find(|(a, s, _)| *a == axis && *s == sign).map_or("?", |(_, _, name)| name),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Binding> {
+        KEY_NAMES.iter().find(|(_, n)| *n == name).map(|(k, _)| Binding::Key(*k))
+            .or_else(|| MOUSE_NAMES.iter().find(|(_, n)| *n == name).map(|(b, _)| Binding::Mouse(*b)))
+            .or_else(|| PAD_NAMES.iter().find(|(_, n)| *n == name).map(|(b, _)| Binding::Pad(*b)))
+            .or_else(|| AXIS_NAMES.iter().find(|(_, _, n)| *n == name).map(|(a, s, _)| Binding::Axis(*a, *s)))
+    }
+
+    fn is_down(self, window: &RaylibHandle, dead_zone: f32) -> bool {
+        match self {
+            Binding::Key(key) => window.is_key_down(key),
+            Binding::Mouse(button) => window.is_mouse_button_down(button),
+            Binding::Pad(button) => window.is_gamepad_available(GAMEPAD) && window.is_gamepad_button_down(GAMEPAD, button),
+            Binding::Axis(axis, sign) => {
+                window.is_gamepad_available(GAMEPAD)
+                    && window.get_gamepad_axis_movement(GAMEPAD, axis) * sign as f32 > dead_zone
+            }
+        }
+    }
+
+    // Fuerza de 0 a 1: los botones son 0 o 1 y los ejes se reescalan desde la zona muerta
+    fn strength(self, window: &RaylibHandle, dead_zone: f32) -> f32 {
+        match self {
+            Binding::Axis(axis, sign) if window.is_gamepad_available(GAMEPAD) => {
+                apply_dead_zone(window.get_gamepad_axis_movement(GAMEPAD, axis) * sign as f32, dead_zone).max(0.0)
+            }
+            _ if self.is_down(window, dead_zone) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    // Los ejes no tienen "recién presionado" en raylib; eso lo lleva `Controls::update_axes`
+    fn is_pressed(self, window: &RaylibHandle) -> bool {
+        match self {
+            Binding::Key(key) => window.is_key_pressed(key),
+            Binding::Mouse(button) => window.is_mouse_button_pressed(button),
+            Binding::Pad(button) => window.is_gamepad_available(GAMEPAD) && window.is_gamepad_button_pressed(GAMEPAD, button),
+            Binding::Axis(..) => false,
+        }
+    }
+}
+
+pub struct Controls {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pub mouse_sensitivity: f32,
+    pub invert_mouse: bool,
+    pub gamepad_look_speed: f32,
+    pub gamepad_dead_zone: f32,
+    axis_held: HashMap<(i32, i8), f32>, // segundos que lleva inclinado cada eje (eje, dirección)
+    axis_pressed: Vec<(i32, i8)>, // ejes que cuentan como pulsación en este frame
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        use Binding::{Axis, Key, Mouse, Pad};
+        use GamepadAxis::*;
+        use GamepadButton::*;
+        let defaults = [
+            (Action::MoveForward, vec![Key(KeyboardKey::KEY_W), Key(KeyboardKey::KEY_UP), Mouse(MouseButton::MOUSE_BUTTON_LEFT), Axis(GAMEPAD_AXIS_LEFT_Y, -1)]),
+            (Action::MoveBackward, vec![Key(KeyboardKey::KEY_S), Key(KeyboardKey::KEY_DOWN), Mouse(MouseButton::MOUSE_BUTTON_RIGHT), Axis(GAMEPAD_AXIS_LEFT_Y, 1)]),
+            (Action::StrafeLeft, vec![Key(KeyboardKey::KEY_A), Axis(GAMEPAD_AXIS_LEFT_X, -1)]),
+            (Action::StrafeRight, vec![Key(KeyboardKey::KEY_D), Axis(GAMEPAD_AXIS_LEFT_X, 1)]),
+            (Action::TurnLeft, vec![Key(KeyboardKey::KEY_LEFT), Pad(GAMEPAD_BUTTON_LEFT_FACE_LEFT)]),
+            (Action::TurnRight, vec![Key(KeyboardKey::KEY_RIGHT), Pad(GAMEPAD_BUTTON_LEFT_FACE_RIGHT)]),
+            (Action::Sprint, vec![Key(KeyboardKey::KEY_LEFT_SHIFT), Axis(GAMEPAD_AXIS_RIGHT_TRIGGER, 1)]),
+            (Action::Crouch, vec![Key(KeyboardKey::KEY_LEFT_CONTROL), Key(KeyboardKey::KEY_C), Axis(GAMEPAD_AXIS_LEFT_TRIGGER, 1)]),
+            (Action::ToggleMap, vec![Key(KeyboardKey::KEY_M), Pad(GAMEPAD_BUTTON_RIGHT_FACE_UP)]),
+            (Action::Back, vec![Key(KeyboardKey::KEY_TAB), Pad(GAMEPAD_BUTTON_MIDDLE_LEFT)]),
+            (Action::Confirm, vec![Key(KeyboardKey::KEY_ENTER), Pad(GAMEPAD_BUTTON_RIGHT_FACE_DOWN), Pad(GAMEPAD_BUTTON_MIDDLE_RIGHT)]),
+            (Action::MenuUp, vec![Key(KeyboardKey::KEY_UP), Key(KeyboardKey::KEY_W), Pad(GAMEPAD_BUTTON_LEFT_FACE_UP), Axis(GAMEPAD_AXIS_LEFT_Y, -1)]),
+            (Action::MenuDown, vec![Key(KeyboardKey::KEY_DOWN), Key(KeyboardKey::KEY_S), Pad(GAMEPAD_BUTTON_LEFT_FACE_DOWN), Axis(GAMEPAD_AXIS_LEFT_Y, 1)]),
+            (Action::DebugOverlay, vec![Key(KeyboardKey::KEY_F3)]),
+        ];
+        Controls {
+            bindings: defaults.into_iter().collect(),
+            mouse_sensitivity: DEFAULT_MOUSE_SENSITIVITY,
+            invert_mouse: false,
+            gamepad_look_speed: DEFAULT_GAMEPAD_LOOK_SPEED,
+            gamepad_dead_zone: DEFAULT_GAMEPAD_DEAD_ZONE,
+            axis_held: HashMap::new(),
+            axis_pressed: Vec::new(),
+        }
+    }
+}
+
+impl Controls {
+    // Carga los controles; si el archivo no existe se usan los de por defecto.
+    // Las líneas inválidas se ignoran y se devuelven como advertencias.
+    pub fn load(path: impl AsRef<Path>) -> (Controls, Vec<String>) {
+        let path = path.as_ref();
+        let mut controls = Controls::default();
+        let mut warnings = Vec::new();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (controls, warnings),
+            Err(e) => {
+                warnings.push(format!("{}: {}", path.display(), e));
+                return (controls, warnings);
+            }
+        };
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let mut warn = |message: String| warnings.push(format!("{}:{}: {}", path.display(), index + 1, message));
+            let Some((key, value)) = line.split_once('=') else {
+                warn("se esperaba `accion = teclas`".to_string());
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "mouse_sensitivity" => match value.parse::<f32>() {
+                    Ok(sensitivity) if sensitivity > 0.0 => controls.mouse_sensitivity = sensitivity,
+                    _ => warn(format!("sensibilidad `{}` inválida", value)),
+                },
+                "gamepad_look_speed" => match value.parse::<f32>() {
+                    Ok(speed) if speed > 0.0 => controls.gamepad_look_speed = speed,
+                    _ => warn(format!("velocidad `{}` inválida", value)),
+                },
+                "gamepad_dead_zone" => match value.parse::<f32>() {
+                    Ok(dead_zone) if (0.0..1.0).contains(&dead_zone) => controls.gamepad_dead_zone = dead_zone,
+                    _ => warn(format!("zona muerta `{}` fuera de [0, 1)", value)),
+                },
+                "invert_mouse" => match value {
+                    "true" => controls.invert_mouse = true,
+                    "false" => controls.invert_mouse = false,
+                    _ => warn(format!("`{}` no es true/false", value)),
+                },
+                _ => {
+                    let Some(action) = Action::from_key(key) else {
+                        warn(format!("acción `{}` desconocida", key));
+                        continue;
+                    };
+                    let mut bindings = Vec::new();
+                    for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                        match Binding::from_name(name) {
+                            Some(binding) => bindings.push(binding),
+                            None => warn(format!("tecla `{}` desconocida", name)),
+                        }
+                    }
+                    if !bindings.is_empty() {
+                        controls.bindings.insert(action, bindings);
+                    }
+                }
+            }
+        }
+        (controls, warnings)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = String::from("# Controles de My Body Is Ready (se reescribe desde el menú Controls)\n");
+        for action in Action::ALL {
+            let names: Vec<&str> = self.bindings(action).iter().map(|b| b.name()).collect();
+            out.push_str(&format!("{} = {}\n", action.key(), names.join(", ")));
+        }
+        out.push_str(&format!("mouse_sensitivity = {}\n", self.mouse_sensitivity));
+        out.push_str(&format!("invert_mouse = {}\n", self.invert_mouse));
+        out.push_str(&format!("gamepad_look_speed = {}\n", self.gamepad_look_speed));
+        out.push_str(&format!("gamepad_dead_zone = {}\n", self.gamepad_dead_zone));
+        fs::write(path, out)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |b| b.as_slice())
+    }
+
+    // Reemplaza la tecla principal de la acción (las demás se mantienen) y se la quita a
+    // cualquier otra acción, así una tecla nunca dispara dos acciones. Si Confirm o Back se
+    // quedan sin tecla reciben la que se reemplazó; si aun así falta, no se cambia nada.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), String> {
+        let mut bindings = self.bindings.clone();
+        for list in bindings.values_mut() {
+            list.retain(|b| *b != binding);
+        }
+        let list = bindings.entry(action).or_default();
+        let mut replaced = None;
+        match list.first_mut() {
+            Some(primary) => replaced = Some(std::mem::replace(primary, binding)),
+            None => list.push(binding),
+        }
+        for essential in ESSENTIAL {
+            if essential == action || has_key(&bindings, essential) { continue; }
+            if let Some(old @ Binding::Key(_)) = replaced.take() {
+                bindings.entry(essential).or_default().insert(0, old);
+            }
+        }
+        if let Some(missing) = ESSENTIAL.iter().find(|&&essential| !has_key(&bindings, essential)) {
+            return Err(format!("{} needs a keyboard key", missing.label()));
+        }
+        self.bindings = bindings;
+        Ok(())
+    }
+
+    pub fn is_down(&self, window: &RaylibHandle, action: Action) -> bool {
+        self.bindings(action).iter().any(|b| b.is_down(window, self.gamepad_dead_zone))
+    }
+
+    // Qué tanto se pide la acción, de 0 a 1 (el eje más inclinado o 1 si hay un botón apretado)
+    pub fn strength(&self, window: &RaylibHandle, action: Action) -> f32 {
+        self.bindings(action).iter().map(|b| b.strength(window, self.gamepad_dead_zone)).fold(0.0, f32::max)
+    }
+
+    pub fn is_pressed(&self, window: &RaylibHandle, action: Action) -> bool {
+        self.bindings(action).iter().any(|b| match *b {
+            Binding::Axis(axis, sign) => self.axis_pressed.contains(&(axis as i32, sign)),
+            _ => b.is_pressed(window),
+        })
+    }
+
+    // Se llama una vez por frame antes de los menús para que los ejes sirvan de pulsaciones
+    pub fn update_axes(&mut self, window: &RaylibHandle) {
+        let dt = window.get_frame_time();
+        let available = window.is_gamepad_available(GAMEPAD);
+        self.axis_pressed.clear();
+        for &(axis, sign, _) in AXIS_NAMES {
+            let key = (axis as i32, sign);
+            let tilted = available && window.get_gamepad_axis_movement(GAMEPAD, axis) * sign as f32 > self.gamepad_dead_zone;
+            let (held, pressed) = repeat_step(self.axis_held.get(&key).copied(), tilted, dt);
+            match held {
+                Some(time) => self.axis_held.insert(key, time),
+                None => self.axis_held.remove(&key),
+            };
+            if pressed {
+                self.axis_pressed.push(key);
+            }
+        }
+    }
+
+    // Giro de este frame en radianes: mouse más stick derecho
+    pub fn look_delta(&self, window: &RaylibHandle) -> f32 {
+        let sign = if self.invert_mouse { -1.0 } else { 1.0 };
+        let mouse = window.get_mouse_delta().x * self.mouse_sensitivity;
+        let stick = if window.is_gamepad_available(GAMEPAD) {
+            let x = window.get_gamepad_axis_movement(GAMEPAD, GamepadAxis::GAMEPAD_AXIS_RIGHT_X);
+            apply_dead_zone(x, self.gamepad_dead_zone) * self.gamepad_look_speed * window.get_frame_time()
+        } else {
+            0.0
+        };
+        (mouse + stick) * sign
+    }
+}
+
+fn has_key(bindings: &HashMap<Action, Vec<Binding>>, action: Action) -> bool {
+    bindings.get(&action).is_some_and(|list| list.iter().any(|b| matches!(b, Binding::Key(_))))
+}
+
+// Un paso del auto-repetir de un eje: `held` es cuánto llevaba inclinado (None = suelto).
+// Cuenta como pulsación al inclinarlo y luego cada MENU_REPEAT_RATE pasado MENU_REPEAT_DELAY
+fn repeat_step(held: Option<f32>, tilted: bool, dt: f32) -> (Option<f32>, bool) {
+    if !tilted {
+        return (None, false);
+    }
+    let Some(before) = held else {
+        return (Some(0.0), true);
+    };
+    let after = before + dt;
+    let repeats = |time: f32| if time < MENU_REPEAT_DELAY { -1.0 } else { ((time - MENU_REPEAT_DELAY) / MENU_REPEAT_RATE).floor() };
+    (Some(after), repeats(after) > repeats(before))
+}
+
+// Reescala el eje para que empiece en 0 justo al salir de la zona muerta
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() <= dead_zone {
+        return 0.0;
+    }
+    value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+}
+
+// Espera la siguiente tecla o botón para reasignar; solo acepta los que tienen nombre
+pub fn capture_binding(window: &mut RaylibHandle) -> Option<Binding> {
+    if let Some(key) = window.get_key_pressed() {
+        let binding = Binding::Key(key);
+        if key != CANCEL_KEY && binding.name() != "?" {
+            return Some(binding);
+        }
+    }
+    if let Some((button, _)) = MOUSE_NAMES.iter().find(|(button, _)| window.is_mouse_button_pressed(*button)) {
+        return Some(Binding::Mouse(*button));
+    }
+    if !window.is_gamepad_available(GAMEPAD) {
+        return None;
+    }
+    // Solo botones recién apretados: `get_gamepad_button_pressed` repite los que siguen apretados
+    if let Some((button, _)) = PAD_NAMES.iter().find(|(button, _)| window.is_gamepad_button_pressed(GAMEPAD, *button)) {
+        return Some(Binding::Pad(*button));
+    }
+    // Un eje cuenta cuando se inclina más de la mitad en la dirección de la tabla
+    AXIS_NAMES.iter()
+        .find(|(axis, sign, _)| window.get_gamepad_axis_movement(GAMEPAD, *axis) * *sign as f32 > 0.5)
+        .map(|(axis, sign, _)| Binding::Axis(*axis, *sign))
+}
+
+// true cuando no hay ningún botón del mouse o del control apretado ni ningún eje inclinado
+fn all_released(window: &RaylibHandle) -> bool {
+    let mouse = MOUSE_NAMES.iter().any(|(button, _)| window.is_mouse_button_down(*button));
+    let pad = window.is_gamepad_available(GAMEPAD) && (
+        PAD_NAMES.iter().any(|(button, _)| window.is_gamepad_button_down(GAMEPAD, *button))
+            || AXIS_NAMES.iter().any(|(axis, sign, _)| window.get_gamepad_axis_movement(GAMEPAD, *axis) * *sign as f32 > 0.5)
+    );
+    !mouse && !pad
+}
+
+pub struct ControlsMenu { //Pantalla de controles: una fila por acción más los ajustes de mouse y control
+    pub selected: usize,
+    pub waiting: bool, // esperando la nueva tecla de la acción seleccionada
+    armed: bool, // ya se soltó el botón que abrió la espera; antes no se captura nada
+    waited: f32, // segundos esperando la tecla nueva
+    pub message: Option<String>, // por qué no se pudo reasignar la última tecla
+}
+
+impl ControlsMenu {
+    pub const ROWS: usize = Action::ALL.len() + 4;
+    pub const SENSITIVITY_ROW: usize = Action::ALL.len();
+    pub const INVERT_ROW: usize = Action::ALL.len() + 1;
+    pub const LOOK_SPEED_ROW: usize = Action::ALL.len() + 2;
+    pub const DEAD_ZONE_ROW: usize = Action::ALL.len() + 3;
+
+    pub fn new() -> Self {
+        ControlsMenu { selected: 0, waiting: false, armed: false, waited: 0.0, message: None }
+    }
+
+    // Procesa la entrada de un frame; devuelve true cuando hay que volver al menú
+    pub fn update(&mut self, window: &mut RaylibHandle, controls: &mut Controls) -> bool {
+        if self.waiting {
+            self.waited += window.get_frame_time();
+            if window.is_key_pressed(CANCEL_KEY) || self.waited > CAPTURE_TIMEOUT {
+                self.waiting = false;
+                return false;
+            }
+            if !self.armed {
+                self.armed = all_released(window);
+                return false;
+            }
+            if let Some(binding) = capture_binding(window) {
+                self.message = controls.rebind(Action::ALL[self.selected], binding).err();
+                self.waiting = false;
+            }
+            return false;
+        }
+        if controls.is_pressed(window, Action::Back) {
+            return true;
+        }
+        if controls.is_pressed(window, Action::MenuUp) && self.selected > 0 {
+            self.selected -= 1;
+        }
+        if controls.is_pressed(window, Action::MenuDown) && self.selected + 1 < Self::ROWS {
+            self.selected += 1;
+        }
+        // Los ajustes numéricos se cambian con girar izquierda/derecha
+        let change = match (controls.is_pressed(window, Action::TurnLeft), controls.is_pressed(window, Action::TurnRight)) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        match self.selected {
+            Self::SENSITIVITY_ROW => {
+                let step = DEFAULT_MOUSE_SENSITIVITY * 0.25;
+                controls.mouse_sensitivity = (controls.mouse_sensitivity + change * step).max(step);
+            }
+            Self::LOOK_SPEED_ROW => {
+                controls.gamepad_look_speed = (controls.gamepad_look_speed + change * 0.25).max(0.25);
+            }
+            Self::DEAD_ZONE_ROW => {
+                controls.gamepad_dead_zone = (controls.gamepad_dead_zone + change * 0.05).clamp(0.0, 0.9);
+            }
+            Self::INVERT_ROW => {
+                if controls.is_pressed(window, Action::Confirm) {
+                    controls.invert_mouse = !controls.invert_mouse;
+                }
+            }
+            _ => {
+                if controls.is_pressed(window, Action::Confirm) {
+                    self.waiting = true;
+                    self.armed = false;
+                    self.waited = 0.0;
+                    self.message = None;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use KeyboardKey::*;
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.cfg", name, std::process::id()))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut controls = Controls::default();
+        controls.rebind(Action::Sprint, Binding::Key(KEY_SPACE)).unwrap();
+        controls.rebind(Action::ToggleMap, Binding::Axis(GamepadAxis::GAMEPAD_AXIS_RIGHT_Y, 1)).unwrap();
+        controls.mouse_sensitivity = 0.005;
+        controls.invert_mouse = true;
+        controls.gamepad_look_speed = 2.5;
+        controls.gamepad_dead_zone = 0.3;
+
+        let path = temp_file("controls");
+        controls.save(&path).unwrap();
+        let (loaded, warnings) = Controls::load(&path);
+        fs::remove_file(&path).ok();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        for action in Action::ALL {
+            assert_eq!(loaded.bindings(action), controls.bindings(action), "{:?}", action);
+        }
+        assert_eq!(loaded.mouse_sensitivity, 0.005);
+        assert!(loaded.invert_mouse);
+        assert_eq!((loaded.gamepad_look_speed, loaded.gamepad_dead_zone), (2.5, 0.3));
+    }
+
+    #[test]
+    fn bad_lines_are_reported_and_skipped() {
+        let path = temp_file("controls-bad");
+        fs::write(&path, "jump = SPACE\nmove_forward = W, NOPE\nnot a line\nmouse_sensitivity = -1\nsprint =\n").unwrap();
+        let (loaded, warnings) = Controls::load(&path);
+        fs::remove_file(&path).ok();
+        let lines: Vec<bool> = (1..=4).map(|line| warnings.iter().any(|w| w.contains(&format!(":{}:", line)))).collect();
+        assert_eq!(lines, [true, true, true, true], "{:?}", warnings);
+        assert_eq!(warnings.len(), 4);
+        // La tecla buena de la línea se queda y lo que falta usa lo de por defecto
+        assert_eq!(loaded.bindings(Action::MoveForward), [Binding::Key(KEY_W)]);
+        assert_eq!(loaded.mouse_sensitivity, DEFAULT_MOUSE_SENSITIVITY);
+        assert_eq!(loaded.bindings(Action::Sprint), Controls::default().bindings(Action::Sprint));
+    }
+
+    #[test]
+    fn rebind_replaces_the_primary_and_takes_the_key_from_other_actions() {
+        let mut controls = Controls::default();
+        controls.rebind(Action::MoveForward, Binding::Key(KEY_D)).unwrap();
+        assert_eq!(controls.bindings(Action::MoveForward)[0], Binding::Key(KEY_D));
+        assert!(!controls.bindings(Action::MoveForward).contains(&Binding::Key(KEY_W)));
+        assert!(!controls.bindings(Action::StrafeRight).contains(&Binding::Key(KEY_D)));
+    }
+
+    #[test]
+    fn confirm_keeps_a_key_when_back_takes_enter() {
+        let mut controls = Controls::default();
+        controls.rebind(Action::Back, Binding::Key(KEY_ENTER)).unwrap();
+        assert_eq!(controls.bindings(Action::Back)[0], Binding::Key(KEY_ENTER));
+        // Confirm se queda con TAB, la tecla que soltó Back
+        assert_eq!(controls.bindings(Action::Confirm)[0], Binding::Key(KEY_TAB));
+    }
+
+    #[test]
+    fn rebind_that_leaves_confirm_without_a_key_is_refused() {
+        let mut controls = Controls::default();
+        let before = controls.bindings(Action::Confirm).to_vec();
+        assert!(controls.rebind(Action::Confirm, Binding::Mouse(MouseButton::MOUSE_BUTTON_MIDDLE)).is_err());
+        assert_eq!(controls.bindings(Action::Confirm), before);
+    }
+
+    #[test]
+    fn held_axis_presses_once_then_repeats_after_the_delay() {
+        let dt = 0.045; // no cae justo en 0.4 ni en sus repeticiones
+        let mut held = None;
+        let mut presses = Vec::new();
+        for frame in 0..20 {
+            let (next, pressed) = repeat_step(held, true, dt);
+            held = next;
+            if pressed { presses.push(frame); }
+        }
+        // Una al inclinarlo, otra al pasar los 0.4 s (0.405 s) y luego cada 0.1 s
+        assert_eq!(presses, [0, 9, 12, 14, 16, 18]);
+
+        // Al soltarlo se reinicia y la siguiente inclinación vuelve a contar de inmediato
+        assert_eq!(repeat_step(held, false, dt), (None, false));
+        assert_eq!(repeat_step(None, true, dt), (Some(0.0), true));
+    }
+
+    #[test]
+    fn dead_zone_rescales_from_zero() {
+        assert_eq!(apply_dead_zone(0.15, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(1.0, 0.2), 1.0);
+        assert!((apply_dead_zone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+    }
+}
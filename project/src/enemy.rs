@@ -1,75 +1,404 @@
 // enemy.rs
+//
+// Máquina de estados de los enemigos:
+//   Patrol: recorre sus puntos de patrulla en orden (o sigue las paredes si no tiene)
+//   Chase:  ve al jugador y lo persigue por el camino más corto de la cuadricula
+//   Search: perdió de vista al jugador (u oyó un ruido), va a esa posición y busca un rato
+//   Return: se rinde y vuelve a su patrulla
+// Lo que ve y oye cada enemigo lo calcula `perception` y llega en `Senses`; la velocidad,
+// percepción, animación y sonido vienen de su tipo (`archetype`).
 use raylib::prelude::*;
+use crate::archetype::Archetype;
+use crate::collision::{ENEMY_RADIUS, is_clear, move_circle};
 use crate::maze::Maze;
+use crate::nav::{Crowd, NavGrid};
+use crate::perception::Senses;
 
-#[derive(Clone, Copy)]
+pub const DEFAULT_SIGHT: f32 = 6.0; // en celdas
+pub const DEFAULT_FOV: f32 = 2.0 * std::f32::consts::PI / 3.0; // cono de visión de 120°
+pub const DEFAULT_HEARING: f32 = 1.0; // multiplica el radio de los ruidos
+pub const DEFAULT_SEARCH_TIME: f32 = 4.0; // segundos buscando antes de volver
+const ARRIVE_DISTANCE: f32 = 10.0; // pixeles para considerar que llegó a un punto
+const STUCK_TIME: f32 = 1.5; // segundos trabado por otro enemigo antes de cambiar de plan
+const SCAN_INTERVAL: f32 = 1.5; // segundos entre giros de los que no se mueven (speed = 0)
+
+#[derive(Clone, Copy, Debug)]
 pub enum TurnPreference {
     Left,
     Right,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyState {
+    Patrol,
+    Chase,
+    Search,
+    Return,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyParams { //Parámetros de cada enemigo, vienen de su tipo y del nivel
+    pub speed: f32, // pixeles por segundo patrullando
+    pub chase_speed: f32, // pixeles por segundo persiguiendo
+    pub sight: f32, // distancia de visión en celdas
+    pub fov: f32, // en radianes
+    pub hearing: f32,
+    pub search_time: f32,
+    pub turn_preference: TurnPreference,
+    pub omniscient: bool, // siempre sabe dónde está el jugador
+    pub alert_radius: f32, // en celdas; al ver al jugador avisa a los demás (0 = no avisa)
+    pub teleport_interval: f32, // segundos entre teletransportes (0 = nunca)
+}
+
 pub struct Enemy {
     pub pos: Vector2,
+    pub prev_pos: Vector2, // posición del paso anterior, para interpolar al dibujar
     pub texture_key: char,
+    pub state: EnemyState,
+    frames: Vec<char>,
+    frame: usize,
+    frame_time: f32,
+    sound: Option<String>,
+    teleport_timer: f32,
     animation_timer: f32,
     velocity: Vector2,
-    turn_preference: TurnPreference,
-    speed: f32, //velocidad del enemigo
+    params: EnemyParams,
+    waypoints: Vec<Vector2>, // en pixeles
+    next_waypoint: usize,
+    home: Vector2, // donde apareció, para volver si no tiene patrulla
+    last_known: Vector2, // última posición donde vio al jugador
+    search_timer: f32,
+    stuck_timer: f32, // tiempo sin poder avanzar porque otro enemigo tapa el paso
+    scan_timer: f32, // tiempo para el próximo giro si no se mueve
 }
 
 impl Enemy {
-    pub fn new(x: f32, y: f32, turn_preference: TurnPreference, speed: f32) -> Self {
+    pub fn new(pos: Vector2, archetype: &Archetype, waypoints: Vec<Vector2>) -> Self {
         Enemy {
-            pos: Vector2::new(x, y),
-            texture_key: 'e',
+            pos,
+            prev_pos: pos,
+            texture_key: archetype.frames.first().copied().unwrap_or('e'),
+            state: EnemyState::Patrol,
+            frames: archetype.frames.clone(),
+            frame: 0,
+            frame_time: archetype.frame_time,
+            sound: archetype.sound.clone(),
+            teleport_timer: archetype.params.teleport_interval,
             animation_timer: 0.0,
             velocity: Vector2::new(1.0, 0.0),
-            turn_preference,
-            speed,
+            params: archetype.params,
+            waypoints,
+            next_waypoint: 0,
+            home: pos,
+            last_known: pos,
+            search_timer: 0.0,
+            stuck_timer: 0.0,
+            scan_timer: SCAN_INTERVAL,
+        }
+    }
+
+    pub fn velocity(&self) -> Vector2 {
+        self.velocity
+    }
+
+    pub fn params(&self) -> &EnemyParams {
+        &self.params
+    }
+
+    pub fn render_pos(&self, alpha: f32) -> Vector2 {
+        self.prev_pos + (self.pos - self.prev_pos) * alpha
+    }
+
+    pub fn sound(&self) -> Option<&str> {
+        self.sound.as_deref()
+    }
+
+    // Cuenta el tiempo para el próximo teletransporte; no se teletransporta persiguiendo
+    pub fn wants_teleport(&mut self, delta_time: f32) -> bool {
+        if self.params.teleport_interval <= 0.0 || self.state == EnemyState::Chase {
+            return false;
+        }
+        self.teleport_timer -= delta_time;
+        if self.teleport_timer > 0.0 {
+            return false;
         }
+        self.teleport_timer = self.params.teleport_interval;
+        true
     }
 
-    pub fn update(&mut self, delta_time: f32, maze: &Maze, block_size: usize) {
+    // Vuelve a donde apareció, cuando el jugador reaparece en un checkpoint
+    pub fn reset(&mut self) {
+        self.pos = self.home;
+        self.prev_pos = self.home;
+        self.state = EnemyState::Patrol;
+        self.next_waypoint = 0;
+        self.stuck_timer = 0.0;
+        self.teleport_timer = self.params.teleport_interval;
+    }
+
+    pub fn teleport_to(&mut self, pos: Vector2) {
+        self.pos = pos;
+        self.prev_pos = pos;
+        self.state = EnemyState::Patrol;
+    }
+
+    // `id` es el índice del enemigo en `crowd`, para no bloquearse consigo mismo.
+    // Devuelve true si en este paso empezó a perseguir al jugador.
+    pub fn update(&mut self, delta_time: f32, maze: &Maze, nav: &mut NavGrid, crowd: &Crowd, id: usize, block_size: usize, player_pos: Vector2, senses: Senses) -> bool {
+        self.prev_pos = self.pos;
         self.animation_timer += delta_time;
-        if self.animation_timer > 0.4 {
+        if self.animation_timer > self.frame_time && !self.frames.is_empty() {
             self.animation_timer = 0.0;
-            self.texture_key = if self.texture_key == 'e' { 'f' } else { 'e' };
+            self.frame = (self.frame + 1) % self.frames.len();
+            self.texture_key = self.frames[self.frame];
+        }
+
+        let spotted = senses.sees_player && self.state != EnemyState::Chase;
+        if senses.sees_player {
+            self.state = EnemyState::Chase;
+            self.last_known = player_pos;
+        } else if self.state == EnemyState::Chase {
+            self.state = EnemyState::Search;
+            self.search_timer = self.params.search_time;
+        } else if let Some(noise) = senses.heard {
+            // Un ruido lo hace ir a buscar ahí aunque estuviera patrullando o volviendo
+            self.state = EnemyState::Search;
+            self.last_known = noise;
+            self.search_timer = self.params.search_time;
+        }
+
+        let mover = &mut Mover { maze, nav, crowd, id, block_size, delta_time };
+        match self.state {
+            EnemyState::Patrol => {
+                if let Some(&target) = self.waypoints.get(self.next_waypoint) {
+                    if self.walk_to(target, self.params.speed, mover) {
+                        self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+                    }
+                } else {
+                    self.wander(self.params.speed, mover);
+                }
+            }
+            EnemyState::Chase => {
+                self.walk_to(player_pos, self.params.chase_speed, mover);
+            }
+            EnemyState::Search => {
+                // Primero llega a donde lo vio, después da vueltas hasta que se acabe el tiempo.
+                // Los que no se mueven nunca llegarían: buscan girando desde donde están
+                let bs = block_size as f32;
+                if self.params.speed > 0.0 && self.pos.distance_to(self.last_known) > bs / 2.0 {
+                    self.walk_to(self.last_known, self.params.speed, mover);
+                } else {
+                    self.wander(self.params.speed, mover);
+                    self.search_timer -= delta_time;
+                    if self.search_timer <= 0.0 {
+                        self.state = EnemyState::Return;
+                    }
+                }
+            }
+            EnemyState::Return => {
+                let target = self.waypoints.get(self.next_waypoint).copied().unwrap_or(self.home);
+                if self.walk_to(target, self.params.speed, mover) {
+                    self.state = EnemyState::Patrol;
+                }
+            }
+        }
+
+        // Si otro enemigo le tapa el camino mucho tiempo, cambia de plan en vez de esperar
+        // para siempre (dos patrullas de frente en un pasillo se trabarían)
+        if self.stuck_timer > STUCK_TIME {
+            self.stuck_timer = 0.0;
+            match self.state {
+                EnemyState::Patrol if !self.waypoints.is_empty() => {
+                    self.next_waypoint = (self.next_waypoint + self.waypoints.len() - 1) % self.waypoints.len();
+                }
+                EnemyState::Search => self.state = EnemyState::Return,
+                EnemyState::Return => self.state = EnemyState::Patrol,
+                _ => {}
+            }
+        }
+        spotted
+    }
+
+    // Avanza por el camino más corto hacia `target`; devuelve true al llegar
+    fn walk_to(&mut self, target: Vector2, speed: f32, mover: &mut Mover) -> bool {
+        let block_size = mover.block_size;
+        let from = NavGrid::cell_of(self.pos, block_size);
+        let to = NavGrid::cell_of(target, block_size);
+        let next = if from == to {
+            target
+        } else {
+            match mover.nav.next_step(from, to) {
+                Some(cell) => NavGrid::cell_center(cell, block_size),
+                None => {
+                    // No hay camino (objetivo dentro de una pared o encerrado): seguir paredes
+                    self.wander(speed, mover);
+                    return false;
+                }
+            }
+        };
+
+        let delta = next - self.pos;
+        let length = delta.length();
+        if length > 0.0 {
+            self.velocity = delta / length;
+            let step = self.velocity * (speed * mover.delta_time).min(length);
+            if mover.step(self, step).is_none() {
+                return false; // otro enemigo en el camino: esperar
+            }
         }
+        from == to && self.pos.distance_to(target) < ARRIVE_DISTANCE
+    }
 
-        let check_pos = self.pos + self.velocity * (block_size as f32 / 4.0);
-        let grid_x = check_pos.x as usize / block_size;
-        let grid_y = check_pos.y as usize / block_size;
-        
-        let is_wall_ahead = grid_y >= maze.len()
-            || grid_x >= maze[grid_y].len()
-            || maze[grid_y][grid_x] != ' ';
+    // Comportamiento original: avanzar recto y girar hacia el lado preferido al chocar
+    fn wander(&mut self, speed: f32, mover: &mut Mover) {
+        // Después de seguir un camino la dirección puede ser diagonal; se alinea al eje dominante
+        self.velocity = if self.velocity.x.abs() >= self.velocity.y.abs() {
+            Vector2::new(self.velocity.x.signum(), 0.0)
+        } else {
+            Vector2::new(0.0, self.velocity.y.signum())
+        };
+        if speed <= 0.0 {
+            self.scan(mover);
+            return;
+        }
 
-        if is_wall_ahead {
+        // Otro enemigo enfrente cuenta como pared
+        let step = self.velocity * speed * mover.delta_time;
+        let blocked = mover.step(self, step).is_none_or(|hit_wall| hit_wall);
+        if blocked {
+            let block_size = mover.block_size;
             let right_dir = Vector2::new(self.velocity.y, -self.velocity.x);
             let left_dir = Vector2::new(-self.velocity.y, self.velocity.x);
             let back_dir = self.velocity * -1.0;
 
-            let is_clear = |dir: Vector2| -> bool {
-                let check = self.pos + dir * (block_size as f32 / 2.0);
-                let gx = check.x as usize / block_size;
-                let gy = check.y as usize / block_size;
-                gy < maze.len() && gx < maze[gy].len() && maze[gy][gx] == ' '
+            let clear = |dir: Vector2| -> bool {
+                let probe = dir * (block_size as f32 / 2.0);
+                is_clear(mover.maze, self.pos, probe, ENEMY_RADIUS, block_size)
+                    && !mover.crowd.blocks(mover.id, self.pos, self.pos + probe)
             };
 
-            let (preferred_dir, unpreferred_dir) = match self.turn_preference {
+            let (preferred_dir, unpreferred_dir) = match self.params.turn_preference {
                 TurnPreference::Right => (right_dir, left_dir),
                 TurnPreference::Left => (left_dir, right_dir),
             };
 
-            if is_clear(preferred_dir) {
+            if clear(preferred_dir) {
                 self.velocity = preferred_dir;
-            } else if is_clear(unpreferred_dir) {
+            } else if clear(unpreferred_dir) {
                 self.velocity = unpreferred_dir;
             } else {
                 self.velocity = back_dir;
             }
         }
-        
-        self.pos += self.velocity * self.speed * delta_time;
     }
-}
\ No newline at end of file
+
+    // Un paso de largo cero nunca choca, así que sin esto un centinela miraría siempre a +x:
+    // cada SCAN_INTERVAL gira un cuarto de vuelta hacia su lado preferido, saltándose paredes
+    fn scan(&mut self, mover: &Mover) {
+        self.scan_timer -= mover.delta_time;
+        if self.scan_timer > 0.0 {
+            return;
+        }
+        self.scan_timer = SCAN_INTERVAL;
+
+        let turn = |dir: Vector2| match self.params.turn_preference {
+            TurnPreference::Right => Vector2::new(dir.y, -dir.x),
+            TurnPreference::Left => Vector2::new(-dir.y, dir.x),
+        };
+        let block_size = mover.block_size;
+        let mut dir = self.velocity;
+        for _ in 0..4 {
+            dir = turn(dir);
+            let probe = dir * (block_size as f32 / 2.0);
+            if is_clear(mover.maze, self.pos, probe, ENEMY_RADIUS, block_size) {
+                break;
+            }
+        }
+        self.velocity = dir;
+    }
+}
+
+struct Mover<'a> { //Lo que necesita un enemigo para moverse en este paso
+    maze: &'a Maze,
+    nav: &'a mut NavGrid,
+    crowd: &'a Crowd,
+    id: usize,
+    block_size: usize,
+    delta_time: f32,
+}
+
+impl Mover<'_> {
+    // Mueve al enemigo contra las paredes y respeta a los demás enemigos.
+    // None: no se movió porque otro enemigo estorba; Some(true): chocó con una pared
+    fn step(&self, enemy: &mut Enemy, delta: Vector2) -> Option<bool> {
+        let moved = move_circle(self.maze, enemy.pos, delta, ENEMY_RADIUS, self.block_size);
+        if self.crowd.blocks(self.id, enemy.pos, moved.pos) {
+            enemy.stuck_timer += self.delta_time;
+            return None;
+        }
+        enemy.stuck_timer = 0.0;
+        enemy.pos = moved.pos;
+        Some(moved.blocked())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{RaggedRows, parse_maze};
+
+    // Centinela en el medio de un cuarto abierto de 3x3 celdas
+    fn sentry_in_room() -> (Enemy, Maze) {
+        let maze = parse_maze("+--+-+\n|    |\n|    |\n|    |\n+--+-+", RaggedRows::Reject).0;
+        let enemy = Enemy::new(Vector2::new(250.0, 250.0), &Archetype::reggie(0.0), Vec::new());
+        (enemy, maze)
+    }
+
+    fn tick(enemy: &mut Enemy, maze: &Maze, nav: &mut NavGrid, dt: f32, senses: Senses) {
+        let crowd = Crowd::new(std::iter::once(enemy.pos), 100);
+        enemy.update(dt, maze, nav, &crowd, 0, 100, Vector2::new(-1000.0, -1000.0), senses);
+    }
+
+    #[test]
+    fn stationary_enemy_turns_in_place() {
+        let (mut enemy, maze) = sentry_in_room();
+        let mut nav = NavGrid::new(&maze);
+        tick(&mut enemy, &maze, &mut nav, SCAN_INTERVAL / 2.0, Senses::default());
+        assert_eq!((enemy.velocity().x, enemy.velocity().y), (1.0, 0.0), "giró antes de tiempo");
+
+        let mut facings = Vec::new();
+        for _ in 0..4 {
+            tick(&mut enemy, &maze, &mut nav, SCAN_INTERVAL, Senses::default());
+            facings.push((enemy.velocity().x as i32, enemy.velocity().y as i32));
+        }
+        // Prefiere la derecha: desde +x gira hacia arriba (-y), luego -x, +y y de vuelta a +x
+        assert_eq!(facings, vec![(0, -1), (-1, 0), (0, 1), (1, 0)]);
+        assert_eq!((enemy.pos.x, enemy.pos.y), (250.0, 250.0));
+    }
+
+    #[test]
+    fn stationary_enemy_skips_walls_when_turning() {
+        // Pasillo horizontal: arriba y abajo hay pared, solo puede mirar a los lados
+        let maze = parse_maze("+--+-+\n|    |\n+--+-+", RaggedRows::Reject).0;
+        let mut enemy = Enemy::new(Vector2::new(250.0, 150.0), &Archetype::reggie(0.0), Vec::new());
+        let mut nav = NavGrid::new(&maze);
+        tick(&mut enemy, &maze, &mut nav, SCAN_INTERVAL, Senses::default());
+        assert_eq!((enemy.velocity().x, enemy.velocity().y), (-1.0, 0.0));
+        tick(&mut enemy, &maze, &mut nav, SCAN_INTERVAL, Senses::default());
+        assert_eq!((enemy.velocity().x, enemy.velocity().y), (1.0, 0.0));
+    }
+
+    #[test]
+    fn stationary_enemy_finishes_searching() {
+        let (mut enemy, maze) = sentry_in_room();
+        let mut nav = NavGrid::new(&maze);
+        tick(&mut enemy, &maze, &mut nav, 0.1, Senses { sees_player: false, heard: Some(Vector2::new(450.0, 450.0)) });
+        assert_eq!(enemy.state, EnemyState::Search);
+        let mut elapsed = 0.0;
+        while elapsed < DEFAULT_SEARCH_TIME + 1.0 {
+            tick(&mut enemy, &maze, &mut nav, 0.1, Senses::default());
+            elapsed += 0.1;
+        }
+        assert_eq!(enemy.state, EnemyState::Patrol);
+    }
+}
@@ -1,47 +1,139 @@
 //framebuffer.rs
+//
+// Imagen de la pantalla en memoria: un `Vec<u32>` RGBA (`r` en el byte más bajo, igual que
+// `textures::Texels`). No necesita ventana; `presenter.rs` la sube a la GPU cada frame.
 use raylib::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[inline]
+fn pack(color: Color) -> u32 {
+    u32::from_le_bytes([color.r, color.g, color.b, color.a])
+}
 
 pub struct Framebuffer {
     pub width: i32,
     pub height: i32,
-    pub color_buffer: Image,
+    pixels: Vec<u32>,
     background_color: Color,
 }
 
 impl Framebuffer {
     pub fn new(width: i32, height: i32, background_color: Color) -> Self {
-        let color_buffer = Image::gen_image_color(width, height, background_color);
+        let pixels = vec![pack(background_color); (width * height) as usize];
         Framebuffer {
             width,
             height,
-            color_buffer,
+            pixels,
             background_color,
         }
     }
 
     pub fn clear(&mut self) {
-        self.color_buffer.clear_background(self.background_color);
+        self.pixels.fill(pack(self.background_color));
     }
 
     pub fn set_pixel(&mut self, x: i32, y: i32, color: Color) {
         if x >= 0 && x < self.width && y >= 0 && y < self.height {
-            self.color_buffer.draw_pixel(x, y, color);
+            self.pixels[(y * self.width + x) as usize] = pack(color);
         }
     }
-    
-    pub fn get_pixel_color(&mut self, x: i32, y: i32) -> Option<Color> {
+
+    pub fn get_pixel_color(&self, x: i32, y: i32) -> Option<Color> {
         if x >= 0 && x < self.width && y >= 0 && y < self.height {
-            Some(self.color_buffer.get_color(x, y))
+            let [r, g, b, a] = self.pixels[(y * self.width + x) as usize].to_le_bytes();
+            Some(Color::new(r, g, b, a))
         } else {
             None
         }
     }
 
+    // Rellena la fila `y` de `x_start` a `x_end` (sin incluir), recortado a la pantalla
+    pub fn fill_span(&mut self, y: i32, x_start: i32, x_end: i32, color: Color) {
+        if y < 0 || y >= self.height { return; }
+        let (x_start, x_end) = (x_start.max(0), x_end.min(self.width));
+        if x_start >= x_end { return; }
+        let row = (y * self.width) as usize;
+        self.pixels[row + x_start as usize..row + x_end as usize].fill(pack(color));
+    }
+
+    // Escribe una columna hacia abajo empezando en `y_start`; lo que salga de pantalla se ignora
+    pub fn write_column(&mut self, x: i32, y_start: i32, colors: &[Color]) {
+        if x < 0 || x >= self.width { return; }
+        for (y, &color) in (y_start..).zip(colors) {
+            if y >= self.height { break; }
+            if y >= 0 {
+                self.pixels[(y * self.width + x) as usize] = pack(color);
+            }
+        }
+    }
+
     pub fn set_background_color(&mut self, color: Color) {
         self.background_color = color;
     }
 
-    pub fn swap_buffers(&self, window: &mut RaylibHandle, raylib_thread: &RaylibThread) -> Option<Texture2D> {
-        window.load_texture_from_image(raylib_thread, &self.color_buffer).ok()
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    // Guarda la imagen como PPM binario, para revisar el render sin ventana
+    pub fn save_ppm(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in &self.pixels {
+            out.write_all(&pixel.to_le_bytes()[..3])?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(framebuffer: &Framebuffer, y: i32) -> Vec<bool> {
+        (0..framebuffer.width).map(|x| framebuffer.get_pixel_color(x, y).map(pack) == Some(pack(Color::WHITE))).collect()
+    }
+
+    #[test]
+    fn fill_span_clips_to_the_screen() {
+        let mut framebuffer = Framebuffer::new(4, 2, Color::BLACK);
+        framebuffer.fill_span(0, -3, 2, Color::WHITE);
+        assert_eq!(row(&framebuffer, 0), [true, true, false, false]);
+        framebuffer.fill_span(1, 3, 10, Color::WHITE);
+        assert_eq!(row(&framebuffer, 1), [false, false, false, true]);
+        // Filas fuera de la pantalla no hacen nada
+        framebuffer.fill_span(-1, 0, 4, Color::WHITE);
+        framebuffer.fill_span(2, 0, 4, Color::WHITE);
+        assert_eq!(framebuffer.pixels().iter().filter(|&&p| p == pack(Color::WHITE)).count(), 3);
+    }
+
+    #[test]
+    fn fill_span_ignores_empty_and_reversed_ranges() {
+        let mut framebuffer = Framebuffer::new(4, 1, Color::BLACK);
+        framebuffer.fill_span(0, 2, 2, Color::WHITE);
+        framebuffer.fill_span(0, 3, 1, Color::WHITE);
+        framebuffer.fill_span(0, 5, 8, Color::WHITE);
+        framebuffer.fill_span(0, -5, -1, Color::WHITE);
+        assert_eq!(row(&framebuffer, 0), [false; 4]);
+    }
+
+    #[test]
+    fn write_column_clips_to_the_screen() {
+        let mut framebuffer = Framebuffer::new(2, 3, Color::BLACK);
+        let colors = [Color::WHITE; 3];
+        // Empieza arriba de la pantalla: solo cae la última
+        framebuffer.write_column(0, -2, &colors);
+        // Se sale por abajo: solo caen las dos primeras
+        framebuffer.write_column(1, 1, &colors);
+        let lit: Vec<(i32, i32)> = (0..3).flat_map(|y| (0..2).map(move |x| (x, y)))
+            .filter(|&(x, y)| framebuffer.get_pixel_color(x, y).map(pack) == Some(pack(Color::WHITE)))
+            .collect();
+        assert_eq!(lit, [(0, 0), (1, 1), (1, 2)]);
+        // Columnas fuera de la pantalla no hacen nada
+        framebuffer.write_column(-1, 0, &colors);
+        framebuffer.write_column(2, 0, &colors);
+        assert_eq!(framebuffer.pixels().iter().filter(|&&p| p == pack(Color::WHITE)).count(), 3);
     }
 }
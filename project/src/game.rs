@@ -0,0 +1,386 @@
+// game.rs
+//
+// Núcleo de la simulación sin raylib de por medio: recibe un `InputFrame` por paso y
+// devuelve los eventos que pasaron, así lo pueden manejar la ventana, pruebas, bots o
+// el modo headless.
+use raylib::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::collectable::{Collectable, awards_point};
+use crate::enemy::Enemy;
+use crate::health::{Hit, Vitals};
+use crate::level::Level;
+use crate::nav::{Cell, Crowd, NavGrid};
+use crate::perception::{COLLECT_NOISE, Noise, NoiseMap, NoiseTrail, SPRINT_NOISE, Senses, WALK_NOISE, can_see};
+use crate::player::{Player, apply_input};
+
+pub const COLLECT_DISTANCE: f32 = 35.0;
+pub const COLLISION_DISTANCE: f32 = 25.0;
+pub const DEFAULT_TICK_RATE: f32 = 60.0; // pasos de simulación por segundo
+const MAX_FRAME_TIME: f32 = 0.25; // evita la "espiral de la muerte" si un frame se traba
+const AMBUSH_MIN_DISTANCE: u32 = 4; // celdas de pasillo entre el jugador y un teletransporte
+const AMBUSH_MAX_DISTANCE: u32 = 7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameState {
+    Playing,
+    GameOver, //Sin agarrar los coleccionables o que te agarre un enemigo
+    GameWon, //Despues de agarrar los coleccionables
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct InputFrame { //Entrada de un paso de simulación, ya traducida desde teclado/mouse
+    pub forward: f32, // -1 (atrás) a 1 (adelante); con stick puede quedar entre medio
+    pub strafe: f32, // -1 (izquierda) a 1 (derecha)
+    pub turn_left: bool,
+    pub turn_right: bool,
+    pub sprint: bool,
+    pub crouch: bool,
+    pub look_delta: f32, // giro del mouse en radianes (sensibilidad e invertir ya aplicados)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    ItemCollected { texture_key: char, scored: bool },
+    GoalUnlocked,
+    EnemySpotted { enemy: usize }, // índice en `Game::enemies`; empezó a perseguir
+    CheckpointReached { index: usize },
+    PlayerHit { health: u32 },
+    LifeLost { lives: u32 }, // reaparece en el último checkpoint
+    PlayerCaught, // sin vidas: fin de la partida
+    GoalReached,
+}
+
+pub struct Game {
+    pub level: Level,
+    pub player: Player,
+    pub enemies: Vec<Enemy>,
+    pub collectables: Vec<Collectable>,
+    pub score: u32,
+    pub state: GameState,
+    pub vitals: Vitals,
+    pub checkpoint: Vector2, // en pixeles; donde reaparece al perder una vida
+    pub checkpoints_reached: Vec<bool>,
+    pub block_size: usize,
+    pub seed: u64,
+    pub rng: StdRng, // toda la aleatoriedad de la simulación sale de aquí para que las repeticiones coincidan
+    pub noise_trail: NoiseTrail, // solo para el minimapa de depuración
+    pub nav: NavGrid, // campos de distancia compartidos por todos los enemigos
+}
+
+impl Game {
+    pub fn new(level: Level, block_size: usize, seed: u64) -> Self {
+        let nav = NavGrid::new(&level.maze);
+        Game {
+            vitals: Vitals::new(level.damage_rules),
+            checkpoint: level.start * block_size as f32,
+            checkpoints_reached: vec![false; level.checkpoints.len()],
+            player: level.spawn_player(block_size),
+            enemies: level.spawn_enemies(block_size),
+            collectables: level.spawn_collectables(block_size),
+            level,
+            score: 0,
+            state: GameState::Playing,
+            block_size,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            noise_trail: NoiseTrail::new(),
+            nav,
+        }
+    }
+
+    // Huella del estado de la simulación, para comprobar que una repetición terminó igual.
+    // Usa FNV-1a sobre los bits exactos para que no dependa de la versión de Rust.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = StateHasher::new();
+        hash.f32(self.player.pos.x);
+        hash.f32(self.player.pos.y);
+        hash.f32(self.player.a);
+        hash.f32(self.player.velocity.x);
+        hash.f32(self.player.velocity.y);
+        hash.f32(self.player.stamina);
+        for enemy in &self.enemies {
+            hash.f32(enemy.pos.x);
+            hash.f32(enemy.pos.y);
+            hash.f32(enemy.velocity().x);
+            hash.f32(enemy.velocity().y);
+            hash.bytes(&[enemy.state as u8]);
+        }
+        for item in &self.collectables {
+            hash.f32(item.pos.x);
+            hash.f32(item.pos.y);
+        }
+        hash.bytes(&self.score.to_le_bytes());
+        hash.bytes(&self.vitals.health.to_le_bytes());
+        hash.bytes(&self.vitals.lives.to_le_bytes());
+        hash.f32(self.vitals.invulnerable);
+        hash.f32(self.checkpoint.x);
+        hash.f32(self.checkpoint.y);
+        hash.bytes(&[self.state as u8]);
+        hash.finish()
+    }
+
+    pub fn max_score(&self) -> u32 {
+        self.level.score_target
+    }
+
+    pub fn goal_unlocked(&self) -> bool {
+        self.score >= self.level.score_target
+    }
+
+    pub fn step(&mut self, input: InputFrame, dt: f32) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if self.state != GameState::Playing {
+            return events;
+        }
+
+        self.vitals.tick(dt);
+        let was_unlocked = self.goal_unlocked();
+        let player_pos = self.player.pos;
+        let mut collected = Vec::new();
+        self.collectables.retain(|item| {
+            if player_pos.distance_to(item.pos) < COLLECT_DISTANCE {
+                collected.push(item.texture_key);
+                false
+            } else {
+                true
+            }
+        });
+        let mut noises = Vec::new();
+        for texture_key in collected {
+            noises.push(Noise { pos: player_pos, radius: COLLECT_NOISE });
+            let scored = awards_point(texture_key);
+            if scored { self.score += 1; }
+            events.push(GameEvent::ItemCollected { texture_key, scored });
+        }
+        if !was_unlocked && self.goal_unlocked() {
+            events.push(GameEvent::GoalUnlocked);
+        }
+
+        if apply_input(&mut self.player, &input, &self.level.maze, self.block_size, self.goal_unlocked(), dt) {
+            self.state = GameState::GameWon;
+            events.push(GameEvent::GoalReached);
+            return events;
+        }
+
+        let bs = self.block_size as f32;
+        for (index, &cell_pos) in self.level.checkpoints.iter().enumerate() {
+            let pos = cell_pos * bs;
+            if !self.checkpoints_reached[index] && self.player.pos.distance_to(pos) < bs / 2.0 {
+                self.checkpoints_reached[index] = true;
+                self.checkpoint = pos;
+                events.push(GameEvent::CheckpointReached { index });
+            }
+        }
+
+        // Pasos: correr se oye lejos, caminar apenas y agachado no hace ruido
+        if self.player.velocity.length() > 0.0 && !self.player.crouching {
+            let radius = if self.player.sprinting { SPRINT_NOISE } else { WALK_NOISE };
+            noises.push(Noise { pos: self.player.pos, radius });
+        }
+
+        // Primero quién ve al jugador: los centinelas que lo ven avisan con un ruido propio
+        let sights: Vec<bool> = self.enemies.iter()
+            .map(|enemy| {
+                let params = enemy.params();
+                params.omniscient
+                    || can_see(&self.level.maze, enemy.pos, enemy.velocity(), params.fov, params.sight * bs, self.player.pos, self.block_size)
+            })
+            .collect();
+        for (enemy, &sees) in self.enemies.iter().zip(&sights) {
+            if sees && enemy.params().alert_radius > 0.0 {
+                noises.push(Noise { pos: self.player.pos, radius: enemy.params().alert_radius });
+            }
+        }
+        self.nav.track_player(self.player.pos, self.block_size);
+        let noise_map = NoiseMap::new(&mut self.nav, &noises, self.block_size);
+        self.noise_trail.update(&noises, dt);
+
+        let mut crowd = Crowd::new(self.enemies.iter().map(|enemy| enemy.pos), self.block_size);
+        for (id, enemy) in self.enemies.iter_mut().enumerate() {
+            if enemy.wants_teleport(dt) {
+                if let Some(pos) = ambush_spot(&self.nav, &crowd, &mut self.rng, self.block_size) {
+                    enemy.teleport_to(pos);
+                    crowd.moved(id, pos);
+                }
+            }
+            let senses = Senses {
+                sees_player: sights[id],
+                heard: noise_map.loudest_at(&mut self.nav, enemy.pos, enemy.params().hearing),
+            };
+            if enemy.update(dt, &self.level.maze, &mut self.nav, &crowd, id, self.block_size, self.player.pos, senses) {
+                events.push(GameEvent::EnemySpotted { enemy: id });
+            }
+            crowd.moved(id, enemy.pos);
+        }
+        let player_pos = self.player.pos;
+        let attacker = self.enemies.iter().find(|enemy| player_pos.distance_to(enemy.pos) < COLLISION_DISTANCE);
+        if let Some(attacker_pos) = attacker.map(|enemy| enemy.pos) {
+            match self.vitals.hit() {
+                Some(Hit::Hurt) => {
+                    // Empujón lejos del enemigo; si están encima uno del otro, hacia atrás
+                    let away = player_pos - attacker_pos;
+                    let away = if away.length() > 0.0 { away.normalized() } else { Vector2::new(-self.player.a.cos(), -self.player.a.sin()) };
+                    self.player.velocity = away * self.vitals.rules.knockback;
+                    events.push(GameEvent::PlayerHit { health: self.vitals.health });
+                }
+                Some(Hit::LifeLost) => {
+                    self.player = self.level.spawn_player_at(self.checkpoint);
+                    for enemy in &mut self.enemies {
+                        enemy.reset();
+                    }
+                    events.push(GameEvent::LifeLost { lives: self.vitals.lives });
+                }
+                Some(Hit::Dead) => {
+                    self.state = GameState::GameOver;
+                    events.push(GameEvent::PlayerCaught);
+                }
+                None => {} // invulnerable
+            }
+        }
+        events
+    }
+}
+
+// Celda libre a unos pasillos del jugador, elegida con el rng de la partida
+fn ambush_spot(nav: &NavGrid, crowd: &Crowd, rng: &mut StdRng, block_size: usize) -> Option<Vector2> {
+    let field = nav.player_field()?;
+    let cells: Vec<Cell> = field.cells_within(AMBUSH_MIN_DISTANCE, AMBUSH_MAX_DISTANCE)
+        .filter(|&cell| crowd.is_free(cell))
+        .collect();
+    if cells.is_empty() {
+        return None;
+    }
+    Some(NavGrid::cell_center(cells[rng.random_range(0..cells.len())], block_size))
+}
+
+// Acumulador de paso fijo: la simulación avanza siempre en pasos de 1/tick_rate sin
+// importar los FPS, y el render interpola con `alpha()` entre los dos últimos pasos
+pub struct FixedStep {
+    pub tick_rate: f32,
+    accumulator: f32,
+}
+
+impl FixedStep {
+    pub fn new(tick_rate: f32) -> Self {
+        FixedStep { tick_rate, accumulator: 0.0 }
+    }
+
+    pub fn dt(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    // Suma el tiempo del frame y devuelve cuántos pasos hay que simular
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let mut ticks = 0;
+        while self.accumulator >= self.dt() {
+            self.accumulator -= self.dt();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.dt()
+    }
+}
+
+struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self {
+        StateHasher(0xcbf29ce484222325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_bits().to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Subcomando `headless`: corre un nivel sin ventana con el jugador quieto y reporta lo que pasa
+pub fn run_headless(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("uso: headless <nivel> [ticks] [dt]");
+        return 2;
+    };
+    let ticks: u32 = args.get(1).and_then(|t| t.parse().ok()).unwrap_or(600);
+    let dt: f32 = args.get(2).and_then(|t| t.parse().ok()).unwrap_or(1.0 / 60.0);
+    let level = match Level::load(path) {
+        Ok(level) => level,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut game = Game::new(level, 100, 0);
+    for tick in 0..ticks {
+        for event in game.step(InputFrame::default(), dt) {
+            println!("tick {}: {:?}", tick, event);
+        }
+        if game.state != GameState::Playing {
+            break;
+        }
+    }
+    println!("estado final: {:?}, puntos {}/{}, jugador ({:.1}, {:.1}), hash {:016x}",
+        game.state, game.score, game.max_score(), game.player.pos.x, game.player.pos.y, game.state_hash());
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemies_never_share_a_cell_on_hard_level() {
+        let level = Level::load("levels/hard.level").unwrap_or_else(|e| panic!("{}", e));
+        let mut game = Game::new(level, 100, 0);
+        for tick in 0..1200 {
+            game.step(InputFrame::default(), 1.0 / 60.0);
+            let shared = Crowd::new(game.enemies.iter().map(|enemy| enemy.pos), game.block_size).shared_cells();
+            assert_eq!(shared, 0, "tick {}: {} par(es) de enemigos en la misma celda", tick, shared);
+        }
+    }
+
+    #[test]
+    fn long_frame_is_clamped_to_max_frame_time() {
+        // 1/64 s es exacto en f32, así 0.25 s son justo 16 pasos
+        let mut clock = FixedStep::new(64.0);
+        assert_eq!(clock.advance(3.0), 16);
+        assert_eq!(clock.alpha(), 0.0);
+        // Lo que sobra de un frame queda para el siguiente
+        assert_eq!(clock.advance(1.5 / 64.0), 1);
+        assert_eq!(clock.alpha(), 0.5);
+        assert_eq!(clock.advance(0.5 / 64.0), 1);
+        assert_eq!(clock.alpha(), 0.0);
+    }
+
+    #[test]
+    fn steps_and_alpha_add_up_to_the_elapsed_time() {
+        let mut clock = FixedStep::new(60.0);
+        let mut ticks = 0;
+        let mut elapsed = 0.0;
+        for frame in 0..200 {
+            // Frames irregulares, entre 5 y 40 ms
+            let frame_time = 0.005 + (frame % 8) as f32 * 0.005;
+            ticks += clock.advance(frame_time);
+            elapsed += frame_time;
+            let alpha = clock.alpha();
+            assert!((0.0..=1.0).contains(&alpha), "frame {}: alpha {}", frame, alpha);
+            let simulated = (ticks as f32 + alpha) * clock.dt();
+            assert!((simulated - elapsed).abs() < 1e-3, "frame {}: {} simulado de {}", frame, simulated, elapsed);
+        }
+    }
+}
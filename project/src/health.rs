@@ -0,0 +1,124 @@
+// health.rs
+//
+// Vida y vidas del jugador. Un golpe de enemigo quita vida, empuja al jugador y lo deja
+// invulnerable un rato; sin vida pierde una vida y reaparece en el último checkpoint, y sin
+// vidas se acaba la partida. Los valores dependen de la dificultad del nivel y el nivel los
+// puede cambiar con `health = N` y `lives = N`.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DamageRules {
+    pub max_health: u32,
+    pub lives: u32,
+    pub damage: u32, // vida que quita cada golpe
+    pub invulnerable_time: f32, // segundos sin recibir daño después de un golpe
+    pub knockback: f32, // pixeles por segundo con los que sale empujado
+}
+
+impl DamageRules {
+    pub fn for_difficulty(difficulty: &str) -> Self {
+        match difficulty {
+            "easy" => DamageRules { max_health: 3, lives: 3, damage: 1, invulnerable_time: 2.0, knockback: 900.0 },
+            "hard" => DamageRules { max_health: 2, lives: 1, damage: 1, invulnerable_time: 1.0, knockback: 700.0 },
+            "endless" => DamageRules { max_health: 3, lives: 1, damage: 1, invulnerable_time: 1.5, knockback: 800.0 },
+            _ => DamageRules { max_health: 3, lives: 2, damage: 1, invulnerable_time: 1.5, knockback: 800.0 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hit {
+    Hurt, // perdió vida pero sigue
+    LifeLost, // se quedó sin vida y le quedan vidas
+    Dead, // sin vidas
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Vitals {
+    pub health: u32,
+    pub lives: u32,
+    pub invulnerable: f32, // segundos que quedan de invulnerabilidad
+    pub rules: DamageRules,
+}
+
+impl Vitals {
+    pub fn new(rules: DamageRules) -> Self {
+        Vitals { health: rules.max_health, lives: rules.lives, invulnerable: 0.0, rules }
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+    }
+
+    // Aplica un golpe; se ignora si todavía es invulnerable
+    pub fn hit(&mut self) -> Option<Hit> {
+        if self.is_invulnerable() {
+            return None;
+        }
+        self.health = self.health.saturating_sub(self.rules.damage);
+        self.invulnerable = self.rules.invulnerable_time;
+        if self.health > 0 {
+            return Some(Hit::Hurt);
+        }
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives == 0 {
+            return Some(Hit::Dead);
+        }
+        self.health = self.rules.max_health;
+        Some(Hit::LifeLost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(max_health: u32, lives: u32) -> DamageRules {
+        DamageRules { max_health, lives, damage: 1, invulnerable_time: 1.0, knockback: 800.0 }
+    }
+
+    // Golpea dejando pasar la invulnerabilidad del golpe anterior
+    fn hit_after_cooldown(vitals: &mut Vitals) -> Option<Hit> {
+        vitals.tick(vitals.rules.invulnerable_time);
+        vitals.hit()
+    }
+
+    #[test]
+    fn hit_with_health_left_hurts() {
+        let mut vitals = Vitals::new(rules(3, 2));
+        assert_eq!(vitals.hit(), Some(Hit::Hurt));
+        assert_eq!((vitals.health, vitals.lives), (2, 2));
+        assert!(vitals.is_invulnerable());
+    }
+
+    #[test]
+    fn losing_all_health_costs_a_life_and_refills_it() {
+        let mut vitals = Vitals::new(rules(2, 2));
+        assert_eq!(vitals.hit(), Some(Hit::Hurt));
+        assert_eq!(hit_after_cooldown(&mut vitals), Some(Hit::LifeLost));
+        assert_eq!((vitals.health, vitals.lives), (2, 1));
+    }
+
+    #[test]
+    fn last_life_lost_is_dead() {
+        let mut vitals = Vitals::new(rules(1, 1));
+        assert_eq!(vitals.hit(), Some(Hit::Dead));
+        assert_eq!((vitals.health, vitals.lives), (0, 0));
+    }
+
+    #[test]
+    fn hits_while_invulnerable_are_ignored() {
+        let mut vitals = Vitals::new(rules(3, 1));
+        assert_eq!(vitals.hit(), Some(Hit::Hurt));
+        vitals.tick(0.5);
+        assert_eq!(vitals.hit(), None);
+        assert_eq!(vitals.health, 2);
+        vitals.tick(0.5);
+        assert!(!vitals.is_invulnerable());
+        assert_eq!(vitals.hit(), Some(Hit::Hurt));
+        assert_eq!(vitals.health, 1);
+    }
+}
@@ -0,0 +1,19 @@
+// input.rs
+use raylib::prelude::*;
+use crate::controls::{Action, Controls};
+use crate::game::InputFrame;
+
+// Traduce el estado de las acciones de este frame a un InputFrame
+pub fn read_input(window: &RaylibHandle, controls: &Controls) -> InputFrame {
+    let down = |action| controls.is_down(window, action);
+    let strength = |action| controls.strength(window, action);
+    InputFrame {
+        forward: strength(Action::MoveForward) - strength(Action::MoveBackward),
+        strafe: strength(Action::StrafeRight) - strength(Action::StrafeLeft),
+        turn_left: down(Action::TurnLeft),
+        turn_right: down(Action::TurnRight),
+        sprint: down(Action::Sprint),
+        crouch: down(Action::Crouch),
+        look_delta: controls.look_delta(window),
+    }
+}
@@ -0,0 +1,489 @@
+// level.rs
+//
+// Formato de los archivos de nivel (`levels/*.level`): una instrucción `clave = valor`
+// por línea, `#` inicia un comentario. Las posiciones van en celdas (1.5 = centro de la
+// columna/fila 1) y se multiplican por `block_size` al crear las entidades.
+//
+//   name = wiiiiii                 nombre que se muestra en el menú
+//   difficulty = easy              texto libre (easy, hard, ...)
+//   maze = maze.txt                laberinto ASCII, relativo a la carpeta del nivel
+//   ragged = pad                   filas cortas: `pad` (rellena con '#') o `reject` (opcional)
+//   start = 1.5 6.5                posición inicial del jugador
+//   angle = -90                    ángulo inicial en grados (opcional, -90 = hacia arriba)
+//   score = 6                      puntos necesarios para abrir la meta 'g'
+//   item = 1.5 1.5 n               coleccionable: posición y llave de textura
+//   enemy = 1.5 1.5 400 right      enemigo: posición, velocidad y giro preferido (left/right),
+//                                  opcionalmente visión en celdas y velocidad al perseguir
+//   enemy = 7.5 13.5 sentry left   enemigo de un tipo de `enemies.cfg` (ver archetype.rs),
+//                                  el giro es opcional
+//   patrol = 10.5 1.5              punto de patrulla del último `enemy` (se recorren en orden)
+//   checkpoint = 10.5 13.5         al pasar por aquí se reaparece aquí al perder una vida
+//   health = 3                     vida máxima (opcional, por defecto según la dificultad)
+//   lives = 2                      vidas (opcional, por defecto según la dificultad)
+//   floor = +                      textura del piso (opcional, `none` = piso liso)
+//   ceiling = -                    textura del techo (opcional, `none` = sin techo)
+//   floor_at = 19.5 1.5 d          textura del piso solo en esa celda
+//   ceiling_at = 10.5 13.5 none    textura del techo solo en esa celda (`none` = tragaluz)
+//
+// `item`, `enemy`, `patrol`, `checkpoint`, `floor_at` y `ceiling_at` se pueden repetir; un
+// enemigo sin `patrol` sigue las paredes. Son obligatorias `name`, `difficulty`, `maze`,
+// `start` y `score` (un nivel sin `item`, `enemy` ni `checkpoint` es válido). El laberinto puede tener saltos
+// de línea \n, \r\n o \r; su borde debe estar cerrado salvo por las salidas 'g'.
+use raylib::prelude::*;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::collectable::Collectable;
+use std::collections::HashMap;
+use crate::archetype::{ARCHETYPES_FILE, Archetype, load_archetypes};
+use crate::enemy::{Enemy, TurnPreference};
+use crate::health::DamageRules;
+use crate::maze::{Diagnostic, Maze, RaggedRows, load_maze};
+use crate::maze::generate::{GeneratorConfig, generate};
+use crate::nav::Cell;
+use crate::player::Player;
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io { path: String, source: io::Error },
+    Parse { path: String, line: usize, column: usize, message: String },
+    Maze { path: String, diagnostics: Vec<Diagnostic> },
+}
+
+impl LevelError {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        LevelError::Io { path: path.as_ref().display().to_string(), source }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io { path, source } => write!(f, "{}: {}", path, source),
+            LevelError::Parse { path, line, column, message } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            LevelError::Maze { path, diagnostics } => {
+                write!(f, "{}: laberinto inválido", path)?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n  {}:{}", path, diagnostic)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for LevelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LevelError::Io { source, .. } => Some(source),
+            LevelError::Parse { .. } | LevelError::Maze { .. } => None,
+        }
+    }
+}
+
+pub struct EnemySpawn {
+    pub pos: Vector2, // en celdas
+    pub archetype: Archetype, // con el giro (y en el formato viejo velocidad/visión) del nivel
+    pub waypoints: Vec<Vector2>, // en celdas
+}
+
+pub struct ItemSpawn {
+    pub pos: Vector2, // en celdas
+    pub texture_key: char,
+}
+
+#[derive(Clone, Default)]
+pub struct Materials { //Texturas de piso y techo por celda
+    pub floor: Option<char>, // None = piso liso de color
+    pub ceiling: Option<char>, // None = sin techo (negro)
+    pub floor_at: HashMap<Cell, Option<char>>,
+    pub ceiling_at: HashMap<Cell, Option<char>>,
+}
+
+impl Materials {
+    pub fn floor(&self, cell: Cell) -> Option<char> {
+        self.floor_at.get(&cell).copied().unwrap_or(self.floor)
+    }
+
+    pub fn ceiling(&self, cell: Cell) -> Option<char> {
+        self.ceiling_at.get(&cell).copied().unwrap_or(self.ceiling)
+    }
+}
+
+pub struct Level {
+    pub name: String,
+    pub difficulty: String,
+    pub maze: Maze,
+    pub maze_file: String,
+    pub start: Vector2, // en celdas
+    pub start_angle: f32, // en radianes
+    pub enemies: Vec<EnemySpawn>,
+    pub collectables: Vec<ItemSpawn>,
+    pub score_target: u32,
+    pub checkpoints: Vec<Vector2>, // en celdas
+    pub damage_rules: DamageRules,
+    pub materials: Materials,
+    pub warnings: Vec<Diagnostic>, // advertencias del laberinto (filas rellenadas, ...)
+}
+
+impl Level {
+    pub fn load(path: impl AsRef<Path>) -> Result<Level, LevelError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| LevelError::io(path, e))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Level::parse(&source, &path.display().to_string(), base_dir)
+    }
+
+    pub fn parse(source: &str, path: &str, base_dir: &Path) -> Result<Level, LevelError> {
+        let mut name = None;
+        let mut difficulty = None;
+        let mut maze_path: Option<PathBuf> = None;
+        let mut ragged = RaggedRows::Pad;
+        let mut start = None;
+        let mut start_angle = -90.0_f32.to_radians();
+        let mut score_target = None;
+        let mut enemies = Vec::new();
+        let mut collectables = Vec::new();
+        let mut archetypes: Option<HashMap<String, Archetype>> = None; // se carga al primer uso
+        let mut checkpoints = Vec::new();
+        let mut max_health = None;
+        let mut lives = None;
+        let mut materials = Materials::default();
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line_no = index + 1;
+            let line = raw_line.split('#').next().unwrap_or("");
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |column: usize, message: String| LevelError::Parse {
+                path: path.to_string(),
+                line: line_no,
+                column,
+                message,
+            };
+
+            let Some(eq) = line.find('=') else {
+                return Err(error(first_column(line), "se esperaba `clave = valor`".to_string()));
+            };
+            let key = line[..eq].trim();
+            let values = tokens(line, eq + 1);
+            let expect_between = |min: usize, max: usize| -> Result<(), LevelError> {
+                if (min..=max).contains(&values.len()) {
+                    Ok(())
+                } else {
+                    let column = values.get(max).map_or(line.len() + 1, |t| t.0);
+                    let count = if min == max { min.to_string() } else { format!("de {} a {}", min, max) };
+                    Err(error(column, format!("`{}` espera {} valor(es), hay {}", key, count, values.len())))
+                }
+            };
+            let expect = |count: usize| expect_between(count, count);
+            let number = |i: usize| -> Result<f32, LevelError> {
+                let (column, text) = values[i];
+                text.parse::<f32>().map_err(|_| error(column, format!("`{}` no es un número", text)))
+            };
+            let texture = |i: usize| -> Result<char, LevelError> {
+                let (column, text) = values[i];
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(c),
+                    _ => Err(error(column, format!("la textura `{}` debe ser un solo caracter", text))),
+                }
+            };
+            let material = |i: usize| -> Result<Option<char>, LevelError> {
+                if values[i].1 == "none" { Ok(None) } else { texture(i).map(Some) }
+            };
+            let cell = || -> Result<Cell, LevelError> { Ok((number(0)?.floor() as i32, number(1)?.floor() as i32)) };
+
+            match key {
+                "name" => name = Some(line[eq + 1..].trim().to_string()),
+                "difficulty" => { expect(1)?; difficulty = Some(values[0].1.to_string()); }
+                "maze" => { expect(1)?; maze_path = Some(base_dir.join(values[0].1)); }
+                "ragged" => {
+                    expect(1)?;
+                    ragged = match values[0].1 {
+                        "pad" => RaggedRows::Pad,
+                        "reject" => RaggedRows::Reject,
+                        other => return Err(error(values[0].0, format!("modo `{}` desconocido, usa pad o reject", other))),
+                    };
+                }
+                "start" => { expect(2)?; start = Some(Vector2::new(number(0)?, number(1)?)); }
+                "angle" => { expect(1)?; start_angle = number(0)?.to_radians(); }
+                "score" => {
+                    expect(1)?;
+                    let (column, text) = values[0];
+                    score_target = Some(text.parse::<u32>().map_err(|_| error(column, format!("`{}` no es un entero positivo", text)))?);
+                }
+                "health" | "lives" => {
+                    expect(1)?;
+                    let (column, text) = values[0];
+                    let value = text.parse::<u32>().ok().filter(|v| *v > 0)
+                        .ok_or_else(|| error(column, format!("`{}` debe ser un entero mayor que 0", text)))?;
+                    if key == "health" { max_health = Some(value); } else { lives = Some(value); }
+                }
+                "checkpoint" => { expect(2)?; checkpoints.push(Vector2::new(number(0)?, number(1)?)); }
+                "item" => {
+                    expect(3)?;
+                    collectables.push(ItemSpawn { pos: Vector2::new(number(0)?, number(1)?), texture_key: texture(2)? });
+                }
+                "floor" => { expect(1)?; materials.floor = material(0)?; }
+                "ceiling" => { expect(1)?; materials.ceiling = material(0)?; }
+                "floor_at" => { expect(3)?; materials.floor_at.insert(cell()?, material(2)?); }
+                "ceiling_at" => { expect(3)?; materials.ceiling_at.insert(cell()?, material(2)?); }
+                "enemy" => {
+                    let turn = |index: usize| -> Result<TurnPreference, LevelError> {
+                        let (column, text) = values[index];
+                        match text {
+                            "left" => Ok(TurnPreference::Left),
+                            "right" => Ok(TurnPreference::Right),
+                            _ => Err(error(column, format!("giro `{}` desconocido, usa left o right", text))),
+                        }
+                    };
+                    let archetype = if values.len() > 2 && values[2].1.parse::<f32>().is_ok() {
+                        // Formato original: x y velocidad giro [visión] [velocidad al perseguir]
+                        expect_between(4, 6)?;
+                        let mut archetype = Archetype::reggie(number(2)?);
+                        archetype.params.turn_preference = turn(3)?;
+                        if values.len() > 4 { archetype.params.sight = number(4)?; }
+                        if values.len() > 5 { archetype.params.chase_speed = number(5)?; }
+                        archetype
+                    } else {
+                        expect_between(3, 4)?;
+                        if archetypes.is_none() {
+                            archetypes = Some(load_archetypes(base_dir.join(ARCHETYPES_FILE))?);
+                        }
+                        let (column, name) = values[2];
+                        let Some(found) = archetypes.as_ref().and_then(|all| all.get(name)) else {
+                            return Err(error(column, format!("tipo de enemigo `{}` desconocido", name)));
+                        };
+                        let mut archetype = found.clone();
+                        if values.len() > 3 { archetype.params.turn_preference = turn(3)?; }
+                        archetype
+                    };
+                    enemies.push(EnemySpawn { pos: Vector2::new(number(0)?, number(1)?), archetype, waypoints: Vec::new() });
+                }
+                "patrol" => {
+                    expect(2)?;
+                    let point = Vector2::new(number(0)?, number(1)?);
+                    match enemies.last_mut() {
+                        Some(enemy) => enemy.waypoints.push(point),
+                        None => return Err(error(first_column(line), "`patrol` necesita un `enemy` antes".to_string())),
+                    }
+                }
+                _ => return Err(error(first_column(line), format!("clave `{}` desconocida", key))),
+            }
+        }
+
+        let missing = |key: &str| LevelError::Parse {
+            path: path.to_string(),
+            line: source.lines().count() + 1,
+            column: 1,
+            message: format!("falta la clave `{}`", key),
+        };
+        let maze_path = maze_path.ok_or_else(|| missing("maze"))?;
+        let maze_file = maze_path.display().to_string();
+        let (maze, warnings) = load_maze(&maze_file, ragged)?;
+        let difficulty = difficulty.ok_or_else(|| missing("difficulty"))?;
+        let mut damage_rules = DamageRules::for_difficulty(&difficulty);
+        if let Some(max_health) = max_health { damage_rules.max_health = max_health; }
+        if let Some(lives) = lives { damage_rules.lives = lives; }
+        Ok(Level {
+            name: name.ok_or_else(|| missing("name"))?,
+            difficulty,
+            maze,
+            maze_file,
+            start: start.ok_or_else(|| missing("start"))?,
+            start_angle,
+            enemies,
+            collectables,
+            score_target: score_target.ok_or_else(|| missing("score"))?,
+            checkpoints,
+            damage_rules,
+            materials,
+            warnings,
+        })
+    }
+
+    pub fn spawn_player(&self, block_size: usize) -> Player {
+        self.spawn_player_at(self.start * block_size as f32)
+    }
+
+    // Reaparición en un checkpoint (en pixeles), mirando hacia el ángulo inicial del nivel
+    pub fn spawn_player_at(&self, pos: Vector2) -> Player {
+        Player::new(pos, self.start_angle, PI / 3.0)
+    }
+
+    pub fn spawn_enemies(&self, block_size: usize) -> Vec<Enemy> {
+        let bs = block_size as f32;
+        self.enemies.iter()
+            .map(|spawn| Enemy::new(spawn.pos * bs, &spawn.archetype, spawn.waypoints.iter().map(|&p| p * bs).collect()))
+            .collect()
+    }
+
+    pub fn spawn_collectables(&self, block_size: usize) -> Vec<Collectable> {
+        let bs = block_size as f32;
+        self.collectables.iter()
+            .map(|item| Collectable::new(item.pos.x * bs, item.pos.y * bs, item.texture_key))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelSource { //De dónde sale un nivel, para poder volver a cargarlo (repeticiones)
+    File(PathBuf),
+    Endless { seed: u64, depth: u32 },
+}
+
+impl LevelSource {
+    pub fn load(&self) -> Result<Level, LevelError> {
+        match self {
+            LevelSource::File(path) => Level::load(path),
+            LevelSource::Endless { seed, depth } => Ok(generate(&GeneratorConfig::endless(*seed, *depth))),
+        }
+    }
+}
+
+pub struct LevelEntry { //Nivel encontrado en la carpeta de niveles, listo para el menú
+    pub path: PathBuf,
+    pub name: String,
+    pub difficulty: String,
+}
+
+// Busca todos los `*.level` de la carpeta y se queda con los que cargan sin errores.
+// Los que fallan se devuelven aparte para poder reportarlos sin romper el menú.
+pub fn discover_levels(dir: impl AsRef<Path>) -> (Vec<LevelEntry>, Vec<LevelError>) {
+    let dir = dir.as_ref();
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            errors.push(LevelError::io(dir, e));
+            return (entries, errors);
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "level"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match Level::load(&path) {
+            Ok(level) => entries.push(LevelEntry { path, name: level.name, difficulty: level.difficulty }),
+            Err(e) => errors.push(e),
+        }
+    }
+    (entries, errors)
+}
+
+// Columna (1-based) del primer caracter no vacío
+pub(crate) fn first_column(line: &str) -> usize {
+    line.len() - line.trim_start().len() + 1
+}
+
+// Separa los valores por espacios, guardando la columna (1-based) de cada uno
+pub(crate) fn tokens(line: &str, offset: usize) -> Vec<(usize, &str)> {
+    let mut result = Vec::new();
+    let mut start = None;
+    for (i, c) in line[offset..].char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                result.push((offset + s + 1, &line[offset + s..offset + i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        result.push((offset + s + 1, &line[offset + s..]));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "name = prueba\ndifficulty = easy\nmaze = maze.txt\n";
+
+    fn parse(source: &str) -> Result<Level, LevelError> {
+        Level::parse(source, "prueba.level", Path::new("levels"))
+    }
+
+    // (línea, columna, mensaje) del error de formato
+    fn parse_error(source: &str) -> (usize, usize, String) {
+        match parse(source) {
+            Err(LevelError::Parse { line, column, message, .. }) => (line, column, message),
+            Err(e) => panic!("error inesperado: {}", e),
+            Ok(_) => panic!("se esperaba un error en {:?}", source),
+        }
+    }
+
+    #[test]
+    fn valid_level_parses() {
+        let level = parse(&format!("{}start = 1.5 6.5\nscore = 0\n", HEADER)).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!((level.start.x, level.start.y), (1.5, 6.5));
+        assert_eq!(level.score_target, 0);
+    }
+
+    #[test]
+    fn bad_number_points_at_the_value() {
+        let (line, column, message) = parse_error(&format!("{}start = 1.5 abc # comentario\n", HEADER));
+        assert_eq!((line, column), (4, 13));
+        assert!(message.contains("abc"), "{}", message);
+    }
+
+    #[test]
+    fn line_without_equals_points_at_the_first_character() {
+        let (line, column, _) = parse_error("name = a\n\n   garbage\n");
+        assert_eq!((line, column), (3, 4));
+    }
+
+    #[test]
+    fn wrong_value_count_points_past_the_values() {
+        // Faltan valores: la columna queda después del final de la línea
+        let (line, column, _) = parse_error("start = 1.5\n");
+        assert_eq!((line, column), (1, 12));
+        // Sobran valores: la columna es la del primero que sobra
+        let (line, column, _) = parse_error("item = 1 2 c d\n");
+        assert_eq!((line, column), (1, 14));
+    }
+
+    #[test]
+    fn unknown_key_and_orphan_patrol_are_reported() {
+        let (line, column, message) = parse_error("name = a\n  colour = red\n");
+        assert_eq!((line, column), (2, 3));
+        assert!(message.contains("colour"), "{}", message);
+        assert_eq!(parse_error("patrol = 1 1\n").0, 1);
+    }
+
+    #[test]
+    fn bad_values_are_reported_at_their_column() {
+        assert_eq!(parse_error("score = -1\n").1, 9);
+        assert_eq!(parse_error("health = 0\n").1, 10);
+        assert_eq!(parse_error("item = 1 2 ab\n").1, 12);
+        assert_eq!(parse_error("ragged = maybe\n").1, 10);
+        assert_eq!(parse_error("enemy = 1.5 1.5 400 up\n").1, 21);
+        assert_eq!(parse_error("enemy = 1.5 1.5 ghost\n").1, 17);
+    }
+
+    #[test]
+    fn missing_key_is_reported_after_the_last_line() {
+        let (line, column, message) = parse_error(&format!("{}start = 1.5 1.5\n", HEADER));
+        assert_eq!((line, column), (5, 1));
+        assert!(message.contains("score"), "{}", message);
+    }
+
+    #[test]
+    fn missing_maze_file_is_an_io_error() {
+        let result = parse("name = a\ndifficulty = easy\nmaze = no-existe.txt\nstart = 1 1\nscore = 0\n");
+        assert!(matches!(result, Err(LevelError::Io { .. })));
+    }
+}
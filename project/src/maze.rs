@@ -1,15 +1,239 @@
-//maze.rs
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub type Maze = Vec<Vec<char>>;
-
-pub fn load_maze(filename: &str) -> Maze {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| line.unwrap().chars().collect())
-        .collect()
-}
\ No newline at end of file
+//maze.rs
+pub mod generate;
+
+use std::fmt;
+use std::fs;
+use crate::level::LevelError;
+
+const PADDING_WALL: char = '#'; // textura por defecto para rellenar filas cortas
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tile {
+    Empty,
+    Wall(char), // el char es la llave de la textura ('+', '-', '|', ...)
+    Exit,       // la meta 'g', se abre al juntar los coleccionables
+}
+
+impl Tile {
+    pub fn from_char(c: char) -> Tile {
+        match c {
+            ' ' => Tile::Empty,
+            'g' => Tile::Exit,
+            other => Tile::Wall(other),
+        }
+    }
+
+    pub fn texture_key(self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall(c) => c,
+            Tile::Exit => 'g',
+        }
+    }
+
+    pub fn is_open(self) -> bool {
+        self == Tile::Empty
+    }
+}
+
+pub struct Maze {
+    rows: Vec<Vec<Tile>>,
+}
+
+impl Maze {
+    pub fn new(rows: Vec<Vec<Tile>>) -> Self {
+        Maze { rows }
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    // Celda en (columna, fila); None fuera de la cuadricula
+    pub fn get(&self, i: i32, j: i32) -> Option<Tile> {
+        if i < 0 || j < 0 {
+            return None;
+        }
+        self.rows.get(j as usize).and_then(|row| row.get(i as usize)).copied()
+    }
+
+    // Celda que contiene el punto (x, y) en pixeles del mundo
+    pub fn tile_at(&self, x: f32, y: f32, block_size: usize) -> Option<Tile> {
+        let bs = block_size as f32;
+        self.get((x / bs).floor() as i32, (y / bs).floor() as i32)
+    }
+
+    pub fn is_open_at(&self, x: f32, y: f32, block_size: usize) -> bool {
+        self.tile_at(x, y, block_size).is_some_and(Tile::is_open)
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Tile]> {
+        self.rows.iter().map(|row| row.as_slice())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic { //Problema encontrado al leer un laberinto, con su línea/columna (1-based)
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}:{}: {}: {}", self.line, self.column, severity, self.message)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RaggedRows { //Qué hacer con filas más cortas que la más larga
+    Pad,    // rellenar con pared '#' y avisar
+    Reject, // reportar un error
+}
+
+// Lee el laberinto ASCII normalizando saltos de línea (\r\n, \r) y validando que
+// las filas tengan el mismo largo y que el borde esté cerrado salvo por las salidas 'g'.
+// Siempre devuelve una cuadricula; si hay diagnósticos de tipo Error no se debe usar.
+pub fn parse_maze(source: &str, ragged: RaggedRows) -> (Maze, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut push = |severity, line, column, message: String| diagnostics.push(Diagnostic { severity, line, column, message });
+
+    let normalized = source.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<&str> = normalized.split('\n').collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    if width == 0 {
+        push(Severity::Error, 1, 1, "el laberinto está vacío".to_string());
+        return (Maze::new(Vec::new()), diagnostics);
+    }
+
+    let mut rows = Vec::with_capacity(lines.len());
+    for (j, line) in lines.iter().enumerate() {
+        let mut row: Vec<Tile> = Vec::with_capacity(width);
+        for (i, c) in line.chars().enumerate() {
+            if c == '\t' || c.is_control() {
+                push(Severity::Error, j + 1, i + 1, format!("caracter de control {:?} en el laberinto", c));
+                row.push(Tile::Wall(PADDING_WALL));
+            } else {
+                row.push(Tile::from_char(c));
+            }
+        }
+        if row.len() < width {
+            let column = row.len() + 1;
+            match ragged {
+                RaggedRows::Pad => {
+                    push(Severity::Warning, j + 1, column, format!("fila de {} columnas rellenada hasta {}", row.len(), width));
+                    row.resize(width, Tile::Wall(PADDING_WALL));
+                }
+                RaggedRows::Reject => {
+                    push(Severity::Error, j + 1, column, format!("fila de {} columnas, se esperaban {}", row.len(), width));
+                }
+            }
+        }
+        rows.push(row);
+    }
+
+    // El borde tiene que estar cerrado: solo se permiten paredes o salidas
+    let height = rows.len();
+    for (j, row) in rows.iter().enumerate() {
+        for (i, tile) in row.iter().enumerate() {
+            let on_border = j == 0 || j + 1 == height || i == 0 || i + 1 == row.len();
+            if on_border && tile.is_open() {
+                push(Severity::Error, j + 1, i + 1, "hueco en el borde del laberinto (usa 'g' para una salida)".to_string());
+            }
+        }
+    }
+
+    (Maze::new(rows), diagnostics)
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+// Devuelve el laberinto y sus advertencias; cualquier error lo convierte en LevelError
+pub fn load_maze(filename: &str, ragged: RaggedRows) -> Result<(Maze, Vec<Diagnostic>), LevelError> {
+    let source = fs::read_to_string(filename).map_err(|e| LevelError::io(filename, e))?;
+    let (maze, diagnostics) = parse_maze(&source, ragged);
+    if has_errors(&diagnostics) {
+        return Err(LevelError::Maze { path: filename.to_string(), diagnostics });
+    }
+    Ok((maze, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOX: &str = "+--+\n|  g\n+--+";
+
+    fn tiles(maze: &Maze) -> Vec<Vec<Tile>> {
+        maze.rows().map(|row| row.to_vec()).collect()
+    }
+
+    // (severidad, línea, columna) de cada diagnóstico
+    fn positions(diagnostics: &[Diagnostic]) -> Vec<(Severity, usize, usize)> {
+        diagnostics.iter().map(|d| (d.severity, d.line, d.column)).collect()
+    }
+
+    #[test]
+    fn crlf_and_bare_cr_parse_like_lf() {
+        let (expected, diagnostics) = parse_maze(BOX, RaggedRows::Reject);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        for source in [BOX.replace('\n', "\r\n"), BOX.replace('\n', "\r"), format!("\u{feff}{}\r\n\r\n", BOX)] {
+            let (maze, diagnostics) = parse_maze(&source, RaggedRows::Reject);
+            assert!(diagnostics.is_empty(), "{:?}: {:?}", source, diagnostics);
+            assert_eq!(tiles(&maze), tiles(&expected), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn short_rows_are_padded_with_a_warning() {
+        let (maze, diagnostics) = parse_maze("+--+\n|  \n+--+", RaggedRows::Pad);
+        assert_eq!(positions(&diagnostics), [(Severity::Warning, 2, 4)]);
+        assert_eq!(maze.get(3, 1), Some(Tile::Wall(PADDING_WALL)));
+        assert_eq!(maze.width(), 4);
+    }
+
+    #[test]
+    fn short_rows_are_rejected() {
+        let (_, diagnostics) = parse_maze("+--+\n|  \n+--+", RaggedRows::Reject);
+        assert!(has_errors(&diagnostics));
+        assert!(positions(&diagnostics).contains(&(Severity::Error, 2, 4)), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn border_gap_is_reported_at_its_position() {
+        let (_, diagnostics) = parse_maze("+--+\n|  |\n+- +", RaggedRows::Reject);
+        assert_eq!(positions(&diagnostics), [(Severity::Error, 3, 3)]);
+        let (_, diagnostics) = parse_maze("+--+\n   |\n+--+", RaggedRows::Reject);
+        assert_eq!(positions(&diagnostics), [(Severity::Error, 2, 1)]);
+    }
+
+    #[test]
+    fn control_characters_and_empty_mazes_are_errors() {
+        let (_, diagnostics) = parse_maze("+--+\n|\t |\n+--+", RaggedRows::Reject);
+        assert_eq!(positions(&diagnostics), [(Severity::Error, 2, 2)]);
+        let (maze, diagnostics) = parse_maze("\r\n\n", RaggedRows::Pad);
+        assert!(has_errors(&diagnostics));
+        assert_eq!(maze.height(), 0);
+    }
+}
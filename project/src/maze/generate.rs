@@ -0,0 +1,413 @@
+// maze/generate.rs
+//
+// Generador de laberintos en el mismo estilo ASCII que `levels/*.txt`: cada celda del
+// laberinto ocupa 2x1 caracteres de interior ("|  |") y las paredes se dibujan con
+// '+', '-' y '|'. La celda (x, y) queda en la columna 3x+1 y la fila 2y+1 de la cuadricula.
+use raylib::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use crate::archetype::Archetype;
+use crate::enemy::TurnPreference;
+use crate::health::DamageRules;
+use crate::level::{EnemySpawn, ItemSpawn, Level, Materials};
+use crate::maze::{RaggedRows, parse_maze};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Algorithm {
+    Backtracker, // pasillos largos y pocas bifurcaciones
+    Prim,        // muchas ramas cortas
+    Wilson,      // uniforme entre todos los laberintos posibles
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 3] = [Algorithm::Backtracker, Algorithm::Prim, Algorithm::Wilson];
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GeneratorConfig {
+    pub width: usize,  // en celdas, no en caracteres
+    pub height: usize,
+    pub algorithm: Algorithm,
+    pub braid: f32,    // 0.0 = laberinto perfecto, 1.0 = sin callejones sin salida
+    pub seed: u64,
+    pub items: usize,
+    pub enemies: usize,
+    pub enemy_min_distance: usize, // distancia mínima (en celdas) entre el jugador y un enemigo
+    pub enemy_speed: f32,
+}
+
+impl GeneratorConfig {
+    // Configuración del modo infinito: cada nivel es un poco más grande y tiene más enemigos
+    pub fn endless(seed: u64, depth: u32) -> Self {
+        let depth = depth as usize;
+        GeneratorConfig {
+            width: (6 + depth).min(16),
+            height: (4 + depth / 2).min(12),
+            algorithm: Algorithm::ALL[depth % Algorithm::ALL.len()],
+            braid: 0.25,
+            seed: seed.wrapping_add(depth as u64),
+            items: (4 + depth).min(12),
+            enemies: depth.min(10),
+            enemy_min_distance: 6,
+            enemy_speed: 250.0 + 20.0 * depth.min(10) as f32,
+        }
+    }
+}
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+struct Grid { //Pasillos abiertos entre celdas
+    width: usize,
+    height: usize,
+    open_east: Vec<bool>,
+    open_south: Vec<bool>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid { width, height, open_east: vec![false; width * height], open_south: vec![false; width * height] }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    fn neighbor(&self, x: usize, y: usize, (dx, dy): (i32, i32)) -> Option<(usize, usize)> {
+        let nx = x as i32 + dx;
+        let ny = y as i32 + dy;
+        if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+            None
+        } else {
+            Some((nx as usize, ny as usize))
+        }
+    }
+
+    fn carve(&mut self, (x, y): (usize, usize), (nx, ny): (usize, usize)) {
+        if nx > x { let i = self.index(x, y); self.open_east[i] = true; }
+        if nx < x { let i = self.index(nx, ny); self.open_east[i] = true; }
+        if ny > y { let i = self.index(x, y); self.open_south[i] = true; }
+        if ny < y { let i = self.index(nx, ny); self.open_south[i] = true; }
+    }
+
+    fn is_open(&self, x: usize, y: usize, (dx, dy): (i32, i32)) -> bool {
+        match self.neighbor(x, y, (dx, dy)) {
+            None => false,
+            Some((nx, ny)) => match (dx, dy) {
+                (1, 0) => self.open_east[self.index(x, y)],
+                (-1, 0) => self.open_east[self.index(nx, ny)],
+                (0, 1) => self.open_south[self.index(x, y)],
+                _ => self.open_south[self.index(nx, ny)],
+            },
+        }
+    }
+
+    fn exits(&self, x: usize, y: usize) -> usize {
+        DIRECTIONS.iter().filter(|&&dir| self.is_open(x, y, dir)).count()
+    }
+
+    // Distancia en celdas desde `start` a todas las demás
+    fn distances(&self, start: (usize, usize)) -> Vec<usize> {
+        let mut distance = vec![usize::MAX; self.width * self.height];
+        let mut queue = VecDeque::new();
+        distance[self.index(start.0, start.1)] = 0;
+        queue.push_back(start);
+        while let Some((x, y)) = queue.pop_front() {
+            let d = distance[self.index(x, y)];
+            for dir in DIRECTIONS {
+                if !self.is_open(x, y, dir) { continue; }
+                let (nx, ny) = self.neighbor(x, y, dir).unwrap();
+                let ni = self.index(nx, ny);
+                if distance[ni] == usize::MAX {
+                    distance[ni] = d + 1;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        distance
+    }
+}
+
+fn recursive_backtracker(grid: &mut Grid, rng: &mut StdRng) {
+    let mut visited = vec![false; grid.width * grid.height];
+    let start = (rng.random_range(0..grid.width), rng.random_range(0..grid.height));
+    let mut stack = vec![start];
+    visited[grid.index(start.0, start.1)] = true;
+    while let Some(&(x, y)) = stack.last() {
+        let mut candidates: Vec<(usize, usize)> = DIRECTIONS.iter()
+            .filter_map(|&dir| grid.neighbor(x, y, dir))
+            .filter(|&(nx, ny)| !visited[grid.index(nx, ny)])
+            .collect();
+        if candidates.is_empty() {
+            stack.pop();
+            continue;
+        }
+        candidates.shuffle(rng);
+        let next = candidates[0];
+        grid.carve((x, y), next);
+        visited[grid.index(next.0, next.1)] = true;
+        stack.push(next);
+    }
+}
+
+fn prim(grid: &mut Grid, rng: &mut StdRng) {
+    let mut in_maze = vec![false; grid.width * grid.height];
+    let mut frontier: Vec<(usize, usize)> = Vec::new();
+    let start = (rng.random_range(0..grid.width), rng.random_range(0..grid.height));
+    in_maze[grid.index(start.0, start.1)] = true;
+    frontier.extend(DIRECTIONS.iter().filter_map(|&dir| grid.neighbor(start.0, start.1, dir)));
+
+    while !frontier.is_empty() {
+        let cell = frontier.swap_remove(rng.random_range(0..frontier.len()));
+        if in_maze[grid.index(cell.0, cell.1)] { continue; }
+        let mut parents: Vec<(usize, usize)> = DIRECTIONS.iter()
+            .filter_map(|&dir| grid.neighbor(cell.0, cell.1, dir))
+            .filter(|&(nx, ny)| in_maze[grid.index(nx, ny)])
+            .collect();
+        parents.shuffle(rng);
+        grid.carve(cell, parents[0]);
+        in_maze[grid.index(cell.0, cell.1)] = true;
+        for dir in DIRECTIONS {
+            if let Some((nx, ny)) = grid.neighbor(cell.0, cell.1, dir) {
+                if !in_maze[grid.index(nx, ny)] {
+                    frontier.push((nx, ny));
+                }
+            }
+        }
+    }
+}
+
+fn wilson(grid: &mut Grid, rng: &mut StdRng) {
+    let total = grid.width * grid.height;
+    let mut in_maze = vec![false; total];
+    in_maze[rng.random_range(0..total)] = true;
+    let mut next = vec![0usize; total]; // dirección de salida de cada celda en la caminata actual
+
+    for begin in 0..total {
+        if in_maze[begin] { continue; }
+        // Caminata aleatoria hasta tocar el laberinto; sobrescribir `next` borra los ciclos
+        let mut cell = begin;
+        while !in_maze[cell] {
+            let (x, y) = (cell % grid.width, cell / grid.width);
+            let options: Vec<usize> = (0..DIRECTIONS.len()).filter(|&d| grid.neighbor(x, y, DIRECTIONS[d]).is_some()).collect();
+            let dir = options[rng.random_range(0..options.len())];
+            next[cell] = dir;
+            let (nx, ny) = grid.neighbor(x, y, DIRECTIONS[dir]).unwrap();
+            cell = grid.index(nx, ny);
+        }
+        // Tallar el camino sin ciclos
+        let mut cell = begin;
+        while !in_maze[cell] {
+            let (x, y) = (cell % grid.width, cell / grid.width);
+            let (nx, ny) = grid.neighbor(x, y, DIRECTIONS[next[cell]]).unwrap();
+            grid.carve((x, y), (nx, ny));
+            in_maze[cell] = true;
+            cell = grid.index(nx, ny);
+        }
+    }
+}
+
+// Quita callejones sin salida abriendo una pared, de preferencia hacia otro callejón
+fn braid(grid: &mut Grid, rng: &mut StdRng, amount: f32) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if grid.exits(x, y) != 1 || rng.random::<f32>() >= amount { continue; }
+            let mut closed: Vec<(usize, usize)> = DIRECTIONS.iter()
+                .filter(|&&dir| !grid.is_open(x, y, dir))
+                .filter_map(|&dir| grid.neighbor(x, y, dir))
+                .collect();
+            if closed.is_empty() { continue; }
+            closed.shuffle(rng);
+            let target = closed.iter().copied().find(|&(nx, ny)| grid.exits(nx, ny) == 1).unwrap_or(closed[0]);
+            grid.carve((x, y), target);
+        }
+    }
+}
+
+fn to_ascii(grid: &Grid, exit: ((usize, usize), (i32, i32))) -> Vec<String> {
+    let width = grid.width * 3 + 1;
+    let mut rows: Vec<Vec<char>> = Vec::with_capacity(grid.height * 2 + 1);
+    for y in 0..grid.height {
+        let mut top = Vec::with_capacity(width);
+        let mut middle = Vec::with_capacity(width);
+        for x in 0..grid.width {
+            top.push('+');
+            let open = y > 0 && grid.is_open(x, y, (0, -1));
+            top.extend(if open { "  " } else { "--" }.chars());
+            middle.push(if x > 0 && grid.is_open(x, y, (-1, 0)) { ' ' } else { '|' });
+            middle.extend("  ".chars());
+        }
+        top.push('+');
+        middle.push('|');
+        rows.push(top);
+        rows.push(middle);
+    }
+    rows.push((0..grid.width).flat_map(|_| "+--".chars()).chain(std::iter::once('+')).collect());
+
+    // La salida 'g' reemplaza la pared del borde junto a la celda elegida
+    let ((x, y), (dx, dy)) = exit;
+    match (dx, dy) {
+        (1, 0) => rows[2 * y + 1][3 * x + 3] = 'g',
+        (-1, 0) => rows[2 * y + 1][3 * x] = 'g',
+        (0, 1) => { rows[2 * y + 2][3 * x + 1] = 'g'; rows[2 * y + 2][3 * x + 2] = 'g'; }
+        _ => { rows[2 * y][3 * x + 1] = 'g'; rows[2 * y][3 * x + 2] = 'g'; }
+    }
+    rows.into_iter().map(|row| row.into_iter().collect()).collect()
+}
+
+// Centro de la celda en unidades de la cuadricula ASCII (las mismas que usan los `.level`):
+// el interior ocupa las columnas 3x+1 y 3x+2, así que el centro cae en 3x+2
+fn cell_center((x, y): (usize, usize)) -> Vector2 {
+    Vector2::new(3.0 * x as f32 + 2.0, 2.0 * y as f32 + 1.5)
+}
+
+pub fn generate(config: &GeneratorConfig) -> Level {
+    let width = config.width.max(2);
+    let height = config.height.max(2);
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut grid = Grid::new(width, height);
+    match config.algorithm {
+        Algorithm::Backtracker => recursive_backtracker(&mut grid, &mut rng),
+        Algorithm::Prim => prim(&mut grid, &mut rng),
+        Algorithm::Wilson => wilson(&mut grid, &mut rng),
+    }
+    braid(&mut grid, &mut rng, config.braid);
+
+    let start = (rng.random_range(0..width), rng.random_range(0..height));
+    let distance = grid.distances(start);
+    let cells: Vec<(usize, usize)> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect();
+
+    // La salida va en la celda del borde más lejana al jugador
+    let grid_ref = &grid;
+    let exit = cells.iter().copied()
+        .flat_map(|(x, y)| DIRECTIONS.iter().copied()
+            .filter(move |&dir| grid_ref.neighbor(x, y, dir).is_none())
+            .map(move |dir| ((x, y), dir)))
+        .filter(|&(cell, _)| cell != start)
+        .max_by_key(|&((x, y), _)| distance[grid.index(x, y)])
+        .unwrap();
+
+    let start_dir = DIRECTIONS.iter().copied().find(|&dir| grid.is_open(start.0, start.1, dir)).unwrap_or((1, 0));
+    let start_angle = match start_dir {
+        (1, 0) => 0.0,
+        (0, 1) => PI / 2.0,
+        (-1, 0) => PI,
+        _ => -PI / 2.0,
+    };
+
+    // Coleccionables en los callejones sin salida (si no alcanzan, en celdas lejanas)
+    let mut dead_ends: Vec<(usize, usize)> = cells.iter().copied()
+        .filter(|&cell| cell != start && cell != exit.0 && grid.exits(cell.0, cell.1) == 1)
+        .collect();
+    dead_ends.shuffle(&mut rng);
+    let mut item_cells: Vec<(usize, usize)> = dead_ends.into_iter().take(config.items).collect();
+    if item_cells.len() < config.items {
+        let mut rest: Vec<(usize, usize)> = cells.iter().copied()
+            .filter(|&cell| cell != start && !item_cells.contains(&cell))
+            .collect();
+        rest.sort_by_key(|&(x, y)| std::cmp::Reverse(distance[grid.index(x, y)]));
+        item_cells.extend(rest.into_iter().take(config.items - item_cells.len()));
+    }
+    const ITEM_KEYS: [char; 2] = ['c', 'h']; // las llaves que cuentan puntos
+    let collectables: Vec<ItemSpawn> = item_cells.iter().enumerate()
+        .map(|(i, &cell)| ItemSpawn { pos: cell_center(cell), texture_key: ITEM_KEYS[i % ITEM_KEYS.len()] })
+        .collect();
+
+    // Enemigos lejos del jugador; si el laberinto es chico se usan las celdas más lejanas
+    let mut far: Vec<(usize, usize)> = cells.iter().copied()
+        .filter(|&(x, y)| distance[grid.index(x, y)] >= config.enemy_min_distance)
+        .collect();
+    far.shuffle(&mut rng);
+    if far.len() < config.enemies {
+        let mut by_distance = cells.clone();
+        by_distance.sort_by_key(|&(x, y)| std::cmp::Reverse(distance[grid.index(x, y)]));
+        far = by_distance.into_iter().filter(|&cell| cell != start).collect();
+    }
+    let enemies: Vec<EnemySpawn> = far.into_iter().take(config.enemies).enumerate()
+        .map(|(i, cell)| EnemySpawn {
+            pos: cell_center(cell),
+            archetype: {
+                let mut archetype = Archetype::reggie(config.enemy_speed);
+                archetype.params.turn_preference = if i % 2 == 0 { TurnPreference::Right } else { TurnPreference::Left };
+                archetype
+            },
+            waypoints: Vec::new(),
+        })
+        .collect();
+
+    let ascii = to_ascii(&grid, exit);
+    let (maze, warnings) = parse_maze(&ascii.join("\n"), RaggedRows::Reject);
+    Level {
+        name: format!("Endless {:?} #{}", config.algorithm, config.seed),
+        difficulty: "endless".to_string(),
+        maze,
+        maze_file: format!("<generado, semilla {}>", config.seed),
+        start: cell_center(start),
+        start_angle,
+        enemies,
+        score_target: collectables.len() as u32,
+        checkpoints: Vec::new(),
+        damage_rules: DamageRules::for_difficulty("endless"),
+        materials: Materials { floor: Some('+'), ceiling: Some('-'), ..Materials::default() },
+        collectables,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Tile;
+    use crate::validate::validate;
+
+    fn config(algorithm: Algorithm, braid: f32, seed: u64) -> GeneratorConfig {
+        GeneratorConfig { algorithm, braid, seed, ..GeneratorConfig::endless(seed, 4) }
+    }
+
+    // Lo que define un nivel generado: laberinto, inicio, coleccionables y enemigos
+    fn fingerprint(level: &Level) -> (Vec<Vec<Tile>>, (f32, f32, f32), Vec<(f32, f32, char)>, Vec<(f32, f32)>) {
+        (
+            level.maze.rows().map(|row| row.to_vec()).collect(),
+            (level.start.x, level.start.y, level.start_angle),
+            level.collectables.iter().map(|item| (item.pos.x, item.pos.y, item.texture_key)).collect(),
+            level.enemies.iter().map(|enemy| (enemy.pos.x, enemy.pos.y)).collect(),
+        )
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_level() {
+        for algorithm in Algorithm::ALL {
+            let a = generate(&config(algorithm, 0.25, 99));
+            let b = generate(&config(algorithm, 0.25, 99));
+            assert_eq!(fingerprint(&a), fingerprint(&b), "{:?}", algorithm);
+            let other = generate(&config(algorithm, 0.25, 100));
+            assert_ne!(fingerprint(&a).0, fingerprint(&other).0, "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn every_algorithm_and_braid_is_winnable() {
+        for algorithm in Algorithm::ALL {
+            for braid in [0.0, 0.5, 1.0] {
+                for seed in 0..5 {
+                    let level = generate(&config(algorithm, braid, seed));
+                    let report = validate(&level);
+                    let problems: Vec<String> = report.problems.iter().map(|p| p.to_string()).collect();
+                    assert!(report.is_ok(), "{:?} braid {} semilla {}: {:?}", algorithm, braid, seed, problems);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawns_sit_in_the_middle_of_their_corridor() {
+        let level = generate(&config(Algorithm::Backtracker, 0.0, 7));
+        for pos in std::iter::once(level.start).chain(level.collectables.iter().map(|item| item.pos)) {
+            // El interior de una celda son las columnas 3x+1 y 3x+2: el centro es 3x+2
+            assert_eq!(pos.x % 3.0, 2.0, "{:?}", (pos.x, pos.y));
+            assert_eq!(pos.y % 2.0, 1.5, "{:?}", (pos.x, pos.y));
+        }
+    }
+}
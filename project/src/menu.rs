@@ -0,0 +1,93 @@
+// menu.rs
+use raylib::prelude::*;
+use crate::level::LevelEntry;
+
+pub const VISIBLE_ROWS: usize = 4; // cuantas entradas caben en la parte de abajo de la pantalla
+
+pub enum MenuEntry {
+    File(LevelEntry),
+    Endless, // laberintos generados uno tras otro
+    Controls, // pantalla para cambiar las teclas
+}
+
+impl MenuEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            MenuEntry::File(entry) => &entry.name,
+            MenuEntry::Endless => "Endless",
+            MenuEntry::Controls => "Controls",
+        }
+    }
+
+    pub fn difficulty(&self) -> &str {
+        match self {
+            MenuEntry::File(entry) => &entry.difficulty,
+            MenuEntry::Endless => "endless",
+            MenuEntry::Controls => "settings",
+        }
+    }
+}
+
+pub struct LevelMenu { //Lista de niveles con selección y scroll
+    pub entries: Vec<MenuEntry>,
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl LevelMenu {
+    pub fn new(levels: Vec<LevelEntry>) -> Self {
+        let mut entries: Vec<MenuEntry> = levels.into_iter().map(MenuEntry::File).collect();
+        entries.push(MenuEntry::Endless);
+        entries.push(MenuEntry::Controls);
+        LevelMenu { entries, selected: 0, scroll: 0 }
+    }
+
+    pub fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+        self.keep_visible();
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+        self.keep_visible();
+    }
+
+    pub fn selected_entry(&self) -> Option<&MenuEntry> {
+        self.entries.get(self.selected)
+    }
+
+    // Entradas que se dibujan ahora, con su índice real
+    pub fn visible(&self) -> impl Iterator<Item = (usize, &MenuEntry)> {
+        self.entries.iter().enumerate().skip(self.scroll).take(VISIBLE_ROWS)
+    }
+
+    pub fn can_scroll_up(&self) -> bool {
+        self.scroll > 0
+    }
+
+    pub fn can_scroll_down(&self) -> bool {
+        self.scroll + VISIBLE_ROWS < self.entries.len()
+    }
+
+    fn keep_visible(&mut self) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE_ROWS {
+            self.scroll = self.selected + 1 - VISIBLE_ROWS;
+        }
+    }
+}
+
+pub fn difficulty_color(difficulty: &str) -> Color {
+    match difficulty {
+        "easy" => Color::GREEN,
+        "normal" | "medium" => Color::YELLOW,
+        "hard" => Color::RED,
+        "endless" => Color::VIOLET,
+        _ => Color::LIGHTGRAY,
+    }
+}
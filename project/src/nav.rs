@@ -0,0 +1,327 @@
+// nav.rs
+//
+// Navegación compartida por todos los enemigos. El grafo de celdas abiertas se arma una
+// vez por nivel y los caminos salen de campos de distancia (BFS desde el objetivo): cada
+// celda guarda su distancia y la vecina por la que se sigue, así preguntar "¿a dónde voy?"
+// es O(1) sin importar cuántos enemigos haya.
+//
+// El campo hacia el jugador se actualiza cuando el jugador cambia de celda: si pasó a una
+// vecina se reparan las distancias viejas en vez de hacer un BFS nuevo (ver `retarget`).
+// Los demás objetivos (patrullas, ruidos, última posición conocida) se guardan en un caché
+// que descarta el campo que lleva más tiempo sin usarse.
+use raylib::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use crate::collision::ENEMY_RADIUS;
+use crate::maze::{Maze, Tile};
+
+const MAX_CACHED_FIELDS: usize = 32; // al pasarse se descarta el menos usado
+const UNREACHABLE: u32 = u32::MAX;
+
+pub type Cell = (i32, i32);
+
+#[derive(Clone)]
+pub struct FlowField { //Distancias y siguiente paso hacia una celda objetivo
+    target: Cell,
+    width: usize,
+    distance: Vec<u32>,
+    next: Vec<u32>, // índice de la celda vecina más cerca del objetivo (UNREACHABLE si no hay)
+}
+
+impl FlowField {
+    pub fn target(&self) -> Cell {
+        self.target
+    }
+
+    pub fn distance(&self, cell: Cell) -> Option<u32> {
+        let index = self.index(cell)?;
+        let d = self.distance[index];
+        (d != UNREACHABLE).then_some(d)
+    }
+
+    pub fn next(&self, cell: Cell) -> Option<Cell> {
+        let index = self.index(cell)?;
+        let next = self.next[index];
+        (next != UNREACHABLE).then(|| ((next as usize % self.width) as i32, (next as usize / self.width) as i32))
+    }
+
+    // Celdas alcanzables a una distancia entre `min` y `max` (inclusive) del objetivo
+    pub fn cells_within(&self, min: u32, max: u32) -> impl Iterator<Item = Cell> + '_ {
+        self.distance.iter().enumerate()
+            .filter(move |(_, &d)| d != UNREACHABLE && d >= min && d <= max)
+            .map(|(index, _)| ((index % self.width) as i32, (index / self.width) as i32))
+    }
+
+    fn index(&self, (i, j): Cell) -> Option<usize> {
+        if i < 0 || j < 0 || i as usize >= self.width {
+            return None;
+        }
+        let index = j as usize * self.width + i as usize;
+        (index < self.distance.len()).then_some(index)
+    }
+}
+
+pub struct NavGrid {
+    width: usize,
+    height: usize,
+    open: Vec<bool>,
+    neighbors: Vec<Vec<u32>>, // vecinas abiertas de cada celda abierta
+    player_field: Option<FlowField>,
+    cache: HashMap<Cell, (FlowField, u64)>, // campo y el momento en que se usó por última vez
+    clock: u64,
+}
+
+impl NavGrid {
+    pub fn new(maze: &Maze) -> Self {
+        let (width, height) = (maze.width(), maze.height());
+        let open = |i: i32, j: i32| maze.get(i, j) == Some(Tile::Empty);
+        let mut neighbors = vec![Vec::new(); width * height];
+        let mut open_cells = vec![false; width * height];
+        for j in 0..height as i32 {
+            for i in 0..width as i32 {
+                if !open(i, j) { continue; }
+                open_cells[j as usize * width + i as usize] = true;
+                neighbors[j as usize * width + i as usize] = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+                    .map(|(di, dj)| (i + di, j + dj))
+                    .filter(|&(ni, nj)| open(ni, nj))
+                    .map(|(ni, nj)| (nj as usize * width + ni as usize) as u32)
+                    .collect();
+            }
+        }
+        NavGrid { width, height, open: open_cells, neighbors, player_field: None, cache: HashMap::new(), clock: 0 }
+    }
+
+    pub fn cell_of(pos: Vector2, block_size: usize) -> Cell {
+        let bs = block_size as f32;
+        ((pos.x / bs).floor() as i32, (pos.y / bs).floor() as i32)
+    }
+
+    pub fn cell_center(cell: Cell, block_size: usize) -> Vector2 {
+        let bs = block_size as f32;
+        Vector2::new((cell.0 as f32 + 0.5) * bs, (cell.1 as f32 + 0.5) * bs)
+    }
+
+    // Se llama una vez por paso; solo recalcula si el jugador entró a otra celda
+    pub fn track_player(&mut self, player_pos: Vector2, block_size: usize) {
+        let cell = NavGrid::cell_of(player_pos, block_size);
+        self.player_field = match self.player_field.take() {
+            Some(field) if field.target == cell => Some(field),
+            Some(mut field) if self.retarget(&mut field, cell) => Some(field),
+            _ => Some(self.build_field(cell)),
+        };
+    }
+
+    pub fn player_field(&self) -> Option<&FlowField> {
+        self.player_field.as_ref()
+    }
+
+    // Campo hacia cualquier celda (usa el del jugador si coincide)
+    pub fn field_to(&mut self, target: Cell) -> &FlowField {
+        if self.player_field.as_ref().is_some_and(|field| field.target == target) {
+            return self.player_field.as_ref().unwrap();
+        }
+        self.clock += 1;
+        if let Some(entry) = self.cache.get_mut(&target) {
+            entry.1 = self.clock;
+        } else {
+            if self.cache.len() >= MAX_CACHED_FIELDS {
+                let oldest = self.cache.iter().min_by_key(|(_, (_, used))| *used).map(|(&cell, _)| cell);
+                if let Some(oldest) = oldest {
+                    self.cache.remove(&oldest);
+                }
+            }
+            let field = self.build_field(target);
+            self.cache.insert(target, (field, self.clock));
+        }
+        &self.cache[&target].0
+    }
+
+    pub fn next_step(&mut self, from: Cell, to: Cell) -> Option<Cell> {
+        self.field_to(to).next(from)
+    }
+
+    // Mueve el objetivo del campo a una celda vecina del objetivo anterior. Cualquier celda
+    // puede seguir su camino viejo y dar un paso más, así que `d + 1` es una cota; solo bajan
+    // las celdas que quedan más cerca del objetivo nuevo y la onda recorre solo esas. Devuelve
+    // false si la celda no es vecina (teletransporte, reaparición) y hay que rehacerlo completo.
+    fn retarget(&self, field: &mut FlowField, target: Cell) -> bool {
+        let (Some(old), Some(new)) = (field.index(field.target), field.index(target)) else { return false };
+        if !self.open[old] || !self.neighbors[old].contains(&(new as u32)) {
+            return false;
+        }
+        for d in field.distance.iter_mut().filter(|d| **d != UNREACHABLE) {
+            *d += 1;
+        }
+        field.target = target;
+        field.next[old] = new as u32;
+        field.distance[new] = 0;
+        field.next[new] = UNREACHABLE;
+        let mut queue = VecDeque::from([new]);
+        while let Some(current) = queue.pop_front() {
+            let d = field.distance[current] + 1;
+            for &neighbor in &self.neighbors[current] {
+                let neighbor = neighbor as usize;
+                if d < field.distance[neighbor] {
+                    field.distance[neighbor] = d;
+                    field.next[neighbor] = current as u32;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        true
+    }
+
+    fn build_field(&self, target: Cell) -> FlowField {
+        let size = self.width * self.height;
+        let mut field = FlowField { target, width: self.width, distance: vec![UNREACHABLE; size], next: vec![UNREACHABLE; size] };
+        let Some(start) = field.index(target).filter(|&index| self.open[index]) else { return field };
+        field.distance[start] = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            let d = field.distance[current];
+            for &neighbor in &self.neighbors[current] {
+                let neighbor = neighbor as usize;
+                if field.distance[neighbor] == UNREACHABLE {
+                    field.distance[neighbor] = d + 1;
+                    field.next[neighbor] = current as u32; // desde la vecina se llega por la celda actual
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        field
+    }
+}
+
+// Reserva de celdas entre enemigos: cada uno es dueño de la celda donde está y nadie más
+// puede entrar a ella, ni acercarse a menos de `CROWD_SPACING` de otro. Así se reparten en
+// los pasillos en vez de apilarse, y se pueden tapar el paso unos a otros.
+pub const CROWD_SPACING: f32 = 2.0 * ENEMY_RADIUS;
+
+pub struct Crowd {
+    positions: Vec<Vector2>,
+    owners: HashMap<Cell, usize>,
+    block_size: usize,
+}
+
+impl Crowd {
+    pub fn new(positions: impl Iterator<Item = Vector2>, block_size: usize) -> Self {
+        let positions: Vec<Vector2> = positions.collect();
+        let mut owners = HashMap::new();
+        for (index, pos) in positions.iter().enumerate() {
+            owners.entry(NavGrid::cell_of(*pos, block_size)).or_insert(index);
+        }
+        Crowd { positions, owners, block_size }
+    }
+
+    // true si el enemigo `me` no puede ir de `from` a `to`; alejarse de otro siempre se puede
+    pub fn blocks(&self, me: usize, from: Vector2, to: Vector2) -> bool {
+        let from_cell = NavGrid::cell_of(from, self.block_size);
+        let to_cell = NavGrid::cell_of(to, self.block_size);
+        if to_cell != from_cell && self.owners.get(&to_cell).is_some_and(|&owner| owner != me) {
+            return true;
+        }
+        self.positions.iter().enumerate().any(|(other, &pos)| {
+            other != me && to.distance_to(pos) < CROWD_SPACING && to.distance_to(pos) < from.distance_to(pos)
+        })
+    }
+
+    pub fn is_free(&self, cell: Cell) -> bool {
+        !self.owners.contains_key(&cell)
+    }
+
+    // Actualiza la posición y la reserva después de que el enemigo se movió
+    pub fn moved(&mut self, me: usize, pos: Vector2) {
+        let old_cell = NavGrid::cell_of(self.positions[me], self.block_size);
+        let new_cell = NavGrid::cell_of(pos, self.block_size);
+        self.positions[me] = pos;
+        if old_cell != new_cell {
+            if self.owners.get(&old_cell) == Some(&me) {
+                self.owners.remove(&old_cell);
+            }
+            self.owners.entry(new_cell).or_insert(me);
+        }
+    }
+
+    // Pares de enemigos que comparten celda (0 cuando la reserva funciona)
+    pub fn shared_cells(&self) -> usize {
+        let mut count: HashMap<Cell, usize> = HashMap::new();
+        for pos in &self.positions {
+            *count.entry(NavGrid::cell_of(*pos, self.block_size)).or_default() += 1;
+        }
+        count.values().map(|&n| n * n.saturating_sub(1) / 2).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Level;
+
+    // Compara el campo del jugador con un BFS nuevo: mismas distancias, y cada paso baja uno
+    fn assert_matches_bfs(nav: &mut NavGrid, cell: Cell) {
+        let reference = nav.build_field(cell);
+        for j in 0..nav.height as i32 {
+            for i in 0..nav.width as i32 {
+                let from = (i, j);
+                let field = nav.player_field().unwrap();
+                assert_eq!(field.distance(from), reference.distance(from), "distancia de {:?} hacia {:?}", from, cell);
+                match (nav.next_step(from, cell), reference.distance(from)) {
+                    (Some(next), Some(d)) => assert_eq!(reference.distance(next), Some(d - 1), "paso de {:?}", from),
+                    (None, d) => assert!(d.is_none_or(|d| d == 0), "{:?} sin paso", from),
+                    (Some(_), None) => panic!("{:?} no se puede alcanzar pero tiene paso", from),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn player_field_matches_a_fresh_bfs_after_moving() {
+        let level = Level::load("levels/hard.level").unwrap();
+        let mut nav = NavGrid::new(&level.maze);
+        let start = NavGrid::cell_of(level.start * 100.0, 100);
+        // Camina celda por celda hasta la celda más lejana del inicio y luego se teletransporta
+        let from_start = nav.build_field(start);
+        let far = from_start.cells_within(0, u32::MAX).max_by_key(|&cell| from_start.distance(cell)).unwrap();
+        let route = nav.build_field(far);
+        let mut cell = start;
+        nav.track_player(NavGrid::cell_center(cell, 100), 100);
+        assert_matches_bfs(&mut nav, cell);
+        while let Some(next) = route.next(cell) {
+            cell = next;
+            nav.track_player(NavGrid::cell_center(cell, 100), 100);
+            assert_matches_bfs(&mut nav, cell);
+        }
+        nav.track_player(NavGrid::cell_center(start, 100), 100);
+        assert_matches_bfs(&mut nav, start);
+    }
+
+    #[test]
+    fn full_cache_drops_the_least_recently_used_field() {
+        let level = Level::load("levels/hard.level").unwrap();
+        let mut nav = NavGrid::new(&level.maze);
+        let cells: Vec<Cell> = nav.build_field(NavGrid::cell_of(level.start * 100.0, 100)).cells_within(0, u32::MAX).collect();
+        assert!(cells.len() > MAX_CACHED_FIELDS + 1);
+        for &cell in &cells[..MAX_CACHED_FIELDS] {
+            nav.field_to(cell);
+        }
+        nav.field_to(cells[0]); // el primero se vuelve a usar, el segundo queda como el más viejo
+        nav.field_to(cells[MAX_CACHED_FIELDS]);
+        assert_eq!(nav.cache.len(), MAX_CACHED_FIELDS);
+        assert!(nav.cache.contains_key(&cells[0]));
+        assert!(!nav.cache.contains_key(&cells[1]));
+    }
+
+    #[test]
+    fn cannot_enter_a_cell_owned_by_another_enemy() {
+        let crowd = Crowd::new([Vector2::new(150.0, 150.0), Vector2::new(250.0, 150.0)].into_iter(), 100);
+        assert!(crowd.blocks(0, Vector2::new(190.0, 150.0), Vector2::new(205.0, 150.0)));
+        assert!(!crowd.is_free((2, 1)));
+    }
+
+    #[test]
+    fn moving_away_from_another_enemy_is_allowed() {
+        // Los dos en la misma celda y más cerca que CROWD_SPACING: alejarse siempre se puede
+        let crowd = Crowd::new([Vector2::new(150.0, 150.0), Vector2::new(170.0, 150.0)].into_iter(), 100);
+        assert!(!crowd.blocks(0, Vector2::new(150.0, 150.0), Vector2::new(140.0, 150.0)));
+        assert!(crowd.blocks(0, Vector2::new(150.0, 150.0), Vector2::new(155.0, 150.0)));
+    }
+}
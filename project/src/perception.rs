@@ -0,0 +1,95 @@
+// perception.rs
+//
+// Lo que un enemigo puede saber del jugador: verlo (dentro de su cono de visión y sin
+// paredes de por medio, usando el mismo DDA del raycaster) u oírlo. El ruido no se propaga
+// en línea recta sino por los pasillos: se mide con los campos de distancia de `nav`.
+use raylib::prelude::*;
+use crate::caster::{RayLimits, cast_grid};
+use crate::maze::Maze;
+use crate::nav::{Cell, NavGrid};
+
+pub const SPRINT_NOISE: f32 = 5.0; // radio en celdas de pasillo
+pub const WALK_NOISE: f32 = 1.5;
+pub const COLLECT_NOISE: f32 = 7.0;
+pub const TOUCH_DISTANCE: f32 = 0.75; // en celdas: tan cerca se nota aunque esté detrás
+const NOISE_SHOW_TIME: f32 = 0.6; // segundos que se ve un ruido en el minimapa de depuración
+
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub pos: Vector2, // en pixeles
+    pub radius: f32, // en celdas
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Senses { //Resultado de la percepción de un enemigo en este paso
+    pub sees_player: bool,
+    pub heard: Option<Vector2>, // posición del ruido más cercano que oyó
+}
+
+// El objetivo está a menos de `range` pixeles, dentro del cono `fov` (radianes, total)
+// alrededor de `facing` y sin paredes en medio
+pub fn can_see(maze: &Maze, eye: Vector2, facing: Vector2, fov: f32, range: f32, target: Vector2, block_size: usize) -> bool {
+    let to_target = target - eye;
+    let distance = to_target.length();
+    if distance > range {
+        return false;
+    }
+    if distance < TOUCH_DISTANCE * block_size as f32 {
+        return true;
+    }
+    if facing.length() > 0.0 && to_target.normalized().dot(facing.normalized()) < (fov / 2.0).cos() {
+        return false;
+    }
+    let limits = RayLimits { max_distance: distance, void_tile: Some('#') };
+    cast_grid(maze, eye, to_target.y.atan2(to_target.x), block_size, &limits).is_none()
+}
+
+// Ruidos del paso actual con la celda de donde salen. Los campos de distancia no se copian:
+// se piden al caché de `nav`, que los comparte con la navegación de los enemigos
+pub struct NoiseMap {
+    sources: Vec<(Noise, Cell)>,
+    block_size: usize,
+}
+
+impl NoiseMap {
+    // Los pasos del jugador salen de su celda, así que casi siempre reusan el campo del jugador
+    pub fn new(nav: &mut NavGrid, noises: &[Noise], block_size: usize) -> Self {
+        let sources: Vec<(Noise, Cell)> = noises.iter()
+            .map(|noise| (*noise, NavGrid::cell_of(noise.pos, block_size)))
+            .collect();
+        for (_, cell) in &sources {
+            nav.field_to(*cell); // se arman aquí una vez, antes de que los consulten los enemigos
+        }
+        NoiseMap { sources, block_size }
+    }
+
+    // Ruido audible desde `listener` más cercano por el pasillo; `hearing` multiplica el radio
+    pub fn loudest_at(&self, nav: &mut NavGrid, listener: Vector2, hearing: f32) -> Option<Vector2> {
+        let cell = NavGrid::cell_of(listener, self.block_size);
+        self.sources.iter()
+            .filter_map(|(noise, source)| {
+                let steps = nav.field_to(*source).distance(cell)?;
+                (steps as f32 <= noise.radius * hearing).then_some((steps, noise.pos))
+            })
+            .min_by_key(|(steps, _)| *steps)
+            .map(|(_, pos)| pos)
+    }
+}
+
+pub struct NoiseTrail { //Ruidos recientes, solo para dibujarlos en el minimapa de depuración
+    pub noises: Vec<(Noise, f32)>, // ruido y tiempo que le queda en pantalla
+}
+
+impl NoiseTrail {
+    pub fn new() -> Self {
+        NoiseTrail { noises: Vec::new() }
+    }
+
+    pub fn update(&mut self, new_noises: &[Noise], dt: f32) {
+        for (_, ttl) in &mut self.noises {
+            *ttl -= dt;
+        }
+        self.noises.retain(|(_, ttl)| *ttl > 0.0);
+        self.noises.extend(new_noises.iter().map(|noise| (*noise, NOISE_SHOW_TIME)));
+    }
+}
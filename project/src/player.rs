@@ -1,93 +1,211 @@
-//player.rs
-use raylib::prelude::*;
-use std::f32::consts::PI;
-use crate::maze::Maze;
-
-pub struct Player {
-    pub pos: Vector2,
-    pub a: f32,
-    pub fov: f32,
-}
-
-pub fn process_events( //Comprobar si el jugador ha llegado a la meta
-    window: &mut RaylibHandle,
-    player: &mut Player,
-    maze: &Maze,
-    block_size: usize,
-    goal_unlocked: bool,
-) -> bool {
-    const MOVE_SPEED: f32 = 8.0;
-    const ROTATION_SPEED: f32 = PI / 40.0;
-    const MOUSE_SENSITIVITY: f32 = 0.002; // Reducido para mejor control
-
-    // Deshabilitar cursor para capturar el mouse
-    if !window.is_cursor_hidden() {
-        window.disable_cursor();
-    }
-
-    // Obtener el delta del mouse directamente aquí
-    let mouse_delta = window.get_mouse_delta();
-    
-    //Rotación con teclado
-    if window.is_key_down(KeyboardKey::KEY_LEFT) || window.is_key_down(KeyboardKey::KEY_A) {
-        player.a -= ROTATION_SPEED;
-    }
-    if window.is_key_down(KeyboardKey::KEY_RIGHT) || window.is_key_down(KeyboardKey::KEY_D) {
-        player.a += ROTATION_SPEED;
-    }
-
-    // Rotación con mouse
-    player.a += mouse_delta.x * MOUSE_SENSITIVITY;
-
-    // Normalizar el ángulo para evitar overflow
-    if player.a > PI {
-        player.a -= 2.0 * PI;
-    } else if player.a < -PI {
-        player.a += 2.0 * PI;
-    }
-
-    let mut next_pos = player.pos;
-    let mut moved = false;
-
-    //Movimiento con teclado
-    if window.is_key_down(KeyboardKey::KEY_UP) || window.is_key_down(KeyboardKey::KEY_W) {
-        next_pos.x += MOVE_SPEED * player.a.cos();
-        next_pos.y += MOVE_SPEED * player.a.sin();
-        moved = true;
-    }
-    if window.is_key_down(KeyboardKey::KEY_DOWN) || window.is_key_down(KeyboardKey::KEY_S) {
-        next_pos.x -= MOVE_SPEED * player.a.cos();
-        next_pos.y -= MOVE_SPEED * player.a.sin();
-        moved = true;
-    }
-
-    // Movimiento con mouse (botones)
-    if window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
-        next_pos.x += MOVE_SPEED * player.a.cos();
-        next_pos.y += MOVE_SPEED * player.a.sin();
-        moved = true;
-    }
-    if window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
-        next_pos.x -= MOVE_SPEED * player.a.cos();
-        next_pos.y -= MOVE_SPEED * player.a.sin();
-        moved = true;
-    }
-
-    // Verificar colisiones y movimiento
-    if moved {
-        let grid_x = next_pos.x as usize / block_size;
-        let grid_y = next_pos.y as usize / block_size;
-
-        if grid_y < maze.len() && grid_x < maze[grid_y].len() {
-            if goal_unlocked && maze[grid_y][grid_x] == 'g' { //Comprobar si el jugador ha llegado a la meta
-                return true;
-            }
-
-            if maze[grid_y][grid_x] == ' ' {
-                player.pos = next_pos;
-            }
-        }
-    }
-    
-    false // No se ha ganado
-}
\ No newline at end of file
+//player.rs
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::game::InputFrame;
+use crate::collision::{PLAYER_RADIUS, move_circle};
+use crate::maze::Maze;
+
+pub const WALK_SPEED: f32 = 480.0; // pixeles por segundo
+pub const SPRINT_MULTIPLIER: f32 = 1.6;
+pub const CROUCH_MULTIPLIER: f32 = 0.45;
+pub const ACCELERATION: f32 = 3000.0; // pixeles/s² hacia la velocidad deseada
+pub const FRICTION: f32 = 4000.0; // pixeles/s² para frenar cuando no hay entrada
+pub const MAX_STAMINA: f32 = 1.0;
+const STAMINA_DRAIN: f32 = 0.35; // por segundo corriendo
+const STAMINA_REGEN: f32 = 0.25; // por segundo descansando
+const STAMINA_REGEN_DELAY: f32 = 1.0; // segundos sin correr antes de recuperar
+const STAMINA_RECOVER: f32 = 0.25; // al agotarse, hay que recuperar esto antes de volver a correr
+
+pub struct Player {
+    pub pos: Vector2,
+    pub a: f32,
+    pub fov: f32,
+    pub prev_pos: Vector2, // estado del paso anterior, para interpolar al dibujar
+    pub prev_a: f32,
+    pub velocity: Vector2, // pixeles por segundo
+    pub stamina: f32,
+    pub exhausted: bool, // se quedó sin stamina y todavía no recupera lo suficiente
+    pub sprinting: bool,
+    pub crouching: bool,
+    regen_delay: f32,
+}
+
+impl Player {
+    pub fn new(pos: Vector2, a: f32, fov: f32) -> Self {
+        Player {
+            pos,
+            a,
+            fov,
+            prev_pos: pos,
+            prev_a: a,
+            velocity: Vector2::zero(),
+            stamina: MAX_STAMINA,
+            exhausted: false,
+            sprinting: false,
+            crouching: false,
+            regen_delay: 0.0,
+        }
+    }
+
+    // Copia del jugador entre el paso anterior y el actual (alpha en [0, 1])
+    pub fn interpolated(&self, alpha: f32) -> Player {
+        let mut da = self.a - self.prev_a;
+        if da > PI { da -= 2.0 * PI; } else if da < -PI { da += 2.0 * PI; }
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * alpha;
+        Player::new(pos, self.prev_a + da * alpha, self.fov)
+    }
+
+    fn update_stamina(&mut self, wants_sprint: bool, moving: bool, dt: f32) {
+        self.sprinting = wants_sprint && moving && !self.crouching && !self.exhausted && self.stamina > 0.0;
+        if self.sprinting {
+            self.stamina = (self.stamina - STAMINA_DRAIN * dt).max(0.0);
+            self.regen_delay = STAMINA_REGEN_DELAY;
+            if self.stamina == 0.0 {
+                self.exhausted = true;
+            }
+        } else if self.regen_delay > 0.0 {
+            self.regen_delay -= dt;
+        } else {
+            self.stamina = (self.stamina + STAMINA_REGEN * dt).min(MAX_STAMINA);
+            if self.stamina >= STAMINA_RECOVER {
+                self.exhausted = false;
+            }
+        }
+    }
+}
+
+pub fn apply_input( //Comprobar si el jugador ha llegado a la meta
+    player: &mut Player,
+    input: &InputFrame,
+    maze: &Maze,
+    block_size: usize,
+    goal_unlocked: bool,
+    dt: f32,
+) -> bool {
+    const ROTATION_SPEED: f32 = 1.5 * PI; // radianes por segundo
+
+    player.prev_pos = player.pos;
+    player.prev_a = player.a;
+
+    //Rotación con teclado
+    if input.turn_left {
+        player.a -= ROTATION_SPEED * dt;
+    }
+    if input.turn_right {
+        player.a += ROTATION_SPEED * dt;
+    }
+
+    // Rotación con mouse (ya viene en radianes, no depende de dt)
+    player.a += input.look_delta;
+
+    // Normalizar el ángulo para evitar overflow
+    if player.a > PI {
+        player.a -= 2.0 * PI;
+    } else if player.a < -PI {
+        player.a += 2.0 * PI;
+    }
+
+    // Dirección deseada: adelante/atrás más los lados (strafe)
+    let forward = Vector2::new(player.a.cos(), player.a.sin());
+    let right = Vector2::new(-player.a.sin(), player.a.cos());
+    // Con teclado la diagonal mide más que 1; con stick la inclinación da la fracción de la velocidad
+    let axis = |value: f32| if value.is_finite() { value.clamp(-1.0, 1.0) } else { 0.0 };
+    let mut wish = forward * axis(input.forward) + right * axis(input.strafe);
+    if wish.length() > 1.0 { wish = wish.normalized(); }
+    let moving = wish.length() > 0.0;
+
+    player.crouching = input.crouch;
+    player.update_stamina(input.sprint, moving, dt);
+
+    let mut speed = WALK_SPEED;
+    if player.sprinting { speed *= SPRINT_MULTIPLIER; }
+    if player.crouching { speed *= CROUCH_MULTIPLIER; }
+    let target = wish * speed;
+
+    // Acelerar hacia la velocidad deseada, o frenar con fricción si no hay entrada
+    let rate = if moving { ACCELERATION } else { FRICTION };
+    let diff = target - player.velocity;
+    let max_change = rate * dt;
+    if diff.length() <= max_change {
+        player.velocity = target;
+    } else {
+        player.velocity += diff.normalized() * max_change;
+    }
+
+    // Verificar colisiones y movimiento: el jugador es un círculo que se desliza por las paredes
+    if player.velocity.length() > 0.0 {
+        let result = move_circle(maze, player.pos, player.velocity * dt, PLAYER_RADIUS, block_size);
+        player.pos = result.pos;
+        if result.blocked_x { player.velocity.x = 0.0; }
+        if result.blocked_y { player.velocity.y = 0.0; }
+        if goal_unlocked && result.touched_exit { //Comprobar si el jugador ha llegado a la meta
+            return true;
+        }
+    }
+
+    false // No se ha ganado
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{RaggedRows, parse_maze};
+
+    // Cuarto abierto de 10x3 celdas
+    fn room() -> Maze {
+        parse_maze("++++++++++++\n+          +\n+          +\n+          +\n++++++++++++", RaggedRows::Reject).0
+    }
+
+    // Velocidad después de un cuarto de segundo con la misma entrada (acelerando se llega en 0.16 s)
+    fn settled_speed(input: InputFrame) -> f32 {
+        let maze = room();
+        let mut player = Player::new(Vector2::new(150.0, 250.0), 0.0, PI / 3.0);
+        for _ in 0..15 {
+            apply_input(&mut player, &input, &maze, 100, false, 1.0 / 60.0);
+        }
+        player.velocity.length()
+    }
+
+    #[test]
+    fn half_stick_walks_at_half_speed() {
+        let speed = settled_speed(InputFrame { forward: 0.5, ..InputFrame::default() });
+        assert!((speed - WALK_SPEED * 0.5).abs() < 1e-2, "{}", speed);
+    }
+
+    #[test]
+    fn keyboard_diagonal_is_not_faster() {
+        let speed = settled_speed(InputFrame { forward: 1.0, strafe: 1.0, ..InputFrame::default() });
+        assert!((speed - WALK_SPEED).abs() < 1e-2, "{}", speed);
+    }
+
+    #[test]
+    fn non_finite_axes_are_ignored() {
+        let speed = settled_speed(InputFrame { forward: f32::NAN, strafe: f32::INFINITY, ..InputFrame::default() });
+        assert_eq!(speed, 0.0);
+    }
+
+    #[test]
+    fn interpolated_goes_from_previous_to_current_step() {
+        let mut player = Player::new(Vector2::new(100.0, 200.0), 0.5, PI / 3.0);
+        player.pos = Vector2::new(140.0, 180.0);
+        player.a = 0.9;
+
+        let start = player.interpolated(0.0);
+        assert_eq!((start.pos.x, start.pos.y, start.a), (100.0, 200.0, 0.5));
+        let end = player.interpolated(1.0);
+        assert_eq!((end.pos.x, end.pos.y), (140.0, 180.0));
+        assert!((end.a - 0.9).abs() < 1e-6);
+        let middle = player.interpolated(0.5);
+        assert_eq!((middle.pos.x, middle.pos.y), (120.0, 190.0));
+        assert!((middle.a - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn interpolated_angle_takes_the_short_way_across_pi() {
+        let mut player = Player::new(Vector2::zero(), PI - 0.1, PI / 3.0);
+        player.a = -PI + 0.1;
+        // Gira 0.2 rad pasando por PI, no 2PI - 0.2 de vuelta por 0
+        let middle = player.interpolated(0.5);
+        assert!((middle.a.cos() + 1.0).abs() < 1e-4, "{}", middle.a);
+    }
+}
@@ -0,0 +1,38 @@
+// presenter.rs
+//
+// Sube el framebuffer a una sola textura de la GPU que se crea una vez y se actualiza con
+// `update_texture` en cada frame, en lugar de crear una textura nueva por frame.
+use raylib::prelude::*;
+use crate::framebuffer::Framebuffer;
+
+pub struct Presenter {
+    texture: Texture2D,
+    bytes: Vec<u8>, // se reusa entre frames
+    failed: bool, // ya se avisó que la subida falla, para no repetirlo cada frame
+}
+
+impl Presenter {
+    pub fn new(window: &mut RaylibHandle, thread: &RaylibThread, width: i32, height: i32) -> Result<Self, String> {
+        // gen_image_color crea la imagen en RGBA8, el mismo orden de bytes que el framebuffer
+        let image = Image::gen_image_color(width, height, Color::BLACK);
+        let texture = window.load_texture_from_image(thread, &image).map_err(|e| e.to_string())?;
+        Ok(Presenter { texture, bytes: Vec::with_capacity((width * height * 4) as usize), failed: false })
+    }
+
+    pub fn upload(&mut self, framebuffer: &Framebuffer) {
+        self.bytes.clear();
+        self.bytes.extend(framebuffer.pixels().iter().flat_map(|pixel| pixel.to_le_bytes()));
+        match self.texture.update_texture(&self.bytes) {
+            Ok(()) => self.failed = false,
+            Err(e) if !self.failed => {
+                eprintln!("No se pudo actualizar la textura de la pantalla: {}", e);
+                self.failed = true;
+            }
+            Err(_) => {}
+        }
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+}
@@ -0,0 +1,447 @@
+// render.rs
+//
+// Render por software a un `Framebuffer`: piso y techo, paredes por raycasting, sprites y
+// minimapa. No usa la ventana, así que sirve para el subcomando `render` y para las pruebas;
+// `main.rs` solo sube el resultado con `Presenter` y dibuja el HUD encima.
+use raylib::prelude::*;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use crate::caster::{RayLimits, cast_grid, cast_ray};
+use crate::collectable::Collectable;
+use crate::enemy::{Enemy, EnemyState};
+use crate::framebuffer::Framebuffer;
+use crate::game::Game;
+use crate::level::{Level, Materials};
+use crate::maze::{Maze, Tile};
+use crate::nav::NavGrid;
+use crate::perception::NoiseTrail;
+use crate::player::Player;
+use crate::textures::{Filter, TEXTURES_FILE, TextureManager, Wrap};
+
+const FOG_COLOR: Color = Color::new(20, 20, 28, 255); //Color del cielo/niebla cuando el rayo no pega en nada
+const FLOOR_COLOR: Color = Color::new(51, 25, 0, 255); //Piso liso en las celdas sin textura
+pub const FLASHLIGHT_RADIUS: f32 = 600.0; //Radio de la linterna
+const WALL_FILTER: Filter = Filter::Nearest;
+const FLOOR_FILTER: Filter = Filter::Bilinear; // el piso se ve muy de cerca a los pies del jugador
+
+pub fn draw_generic_sprite(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    sprite_pos: Vector2,
+    sprite_texture: char,
+    texture_manager: &TextureManager,
+    flashlight_radius: f32,
+) {
+    let sprite_a = (sprite_pos.y - player.pos.y).atan2(sprite_pos.x - player.pos.x);
+    let mut angle_diff = sprite_a - player.a;
+    while angle_diff > PI { angle_diff -= 2.0 * PI; }
+    while angle_diff < -PI { angle_diff += 2.0 * PI; }
+
+    if angle_diff.abs() > player.fov / 2.0 { return; }
+
+    let sprite_d = player.pos.distance_to(sprite_pos);
+
+    if sprite_d < 20.0 || sprite_d > 400.0 { return; } //Rango de visión para coleccionables
+
+    let screen_height = framebuffer.height as f32;
+    let screen_width = framebuffer.width as f32;
+    let screen_center_x = screen_width / 2.0;
+    let screen_center_y = screen_height / 2.0;
+
+    // La escala del manifiesto crece el sprite hacia arriba: la base queda donde estaba
+    let base_size = (screen_height / sprite_d) * 70.0;
+    let sprite_size = base_size * texture_manager.scale(sprite_texture);
+    let screen_x = ((angle_diff / player.fov) + 0.5) * screen_width;
+
+    let start_x = (screen_x - sprite_size / 2.0).max(0.0) as usize;
+    let start_y = (screen_height / 2.0 + base_size / 2.0 - sprite_size).max(0.0) as usize;
+    let sprite_size_usize = sprite_size as usize;
+    let end_x = (start_x + sprite_size_usize).min(framebuffer.width as usize);
+    let end_y = (start_y + sprite_size_usize).min(framebuffer.height as usize);
+
+    let mips = texture_manager.get(sprite_texture);
+    let texture = mips.level(mips.base().width() as f32 / sprite_size);
+
+    for x in start_x..end_x {
+        for y in start_y..end_y {
+            let u = (x - start_x) as f32 / sprite_size_usize as f32;
+            let v = (y - start_y) as f32 / sprite_size_usize as f32;
+            let color = texture.sample(u, v, Filter::Nearest, Wrap::Clamp);
+            
+            if color.a >= 128 { // los mipmaps lejanos mezclan el borde con lo transparente
+                let dist_from_center = ((x as f32 - screen_center_x).powi(2) + (y as f32 - screen_center_y).powi(2)).sqrt();
+                let flashlight_brightness = if dist_from_center < flashlight_radius {
+                    let falloff = 1.0 - (dist_from_center / flashlight_radius);
+                    falloff * falloff
+                } else { 0.0 };
+                let distance_fade = (1.0 - (sprite_d / 1000.0)).max(0.0);
+                let final_brightness = flashlight_brightness * distance_fade;
+                let final_color = Color::new(
+                    (color.r as f32 * final_brightness) as u8,
+                    (color.g as f32 * final_brightness) as u8,
+                    (color.b as f32 * final_brightness) as u8,
+                    color.a
+                );
+                framebuffer.set_pixel(x as i32, y as i32, final_color);
+            }
+        }
+    }
+}
+
+pub fn render_enemies( //Renderiza los enemigos
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    enemies: &[Enemy],
+    alpha: f32,
+    texture_cache: &TextureManager,
+    flashlight_radius: f32,
+) {
+    for enemy in enemies {
+        draw_generic_sprite(framebuffer, player, enemy.render_pos(alpha), enemy.texture_key, texture_cache, flashlight_radius);
+    }
+}
+
+pub fn render_collectables( //Renderiza los coleccionables
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    collectables: &[Collectable],
+    texture_cache: &TextureManager,
+    flashlight_radius: f32,
+) {
+    for item in collectables {
+        draw_generic_sprite(framebuffer, player, item.pos, item.texture_key, texture_cache, flashlight_radius);
+    }
+}
+
+fn draw_cell(
+    framebuffer: &mut Framebuffer,
+    xo: usize,
+    yo: usize,
+    block_size: usize,
+    cell: Tile,
+) {
+    if cell.is_open() { return; }
+    for y in yo..yo + block_size {
+        framebuffer.fill_span(y as i32, xo as i32, (xo + block_size) as i32, Color::RED);
+    }
+}
+
+pub fn render_maze( //Renderiza el laberinto
+    framebuffer: &mut Framebuffer,
+    maze: &Maze,
+    block_size: usize,
+    player: &Player,
+    ray_limits: &RayLimits,
+) {
+    for (row_index, row) in maze.rows().enumerate() {
+        for (col_index, &cell) in row.iter().enumerate() {
+            let xo = col_index * block_size;
+            let yo = row_index * block_size;
+            draw_cell(framebuffer, xo, yo, block_size, cell);
+        }
+    }
+    let px = player.pos.x as i32;
+    let py = player.pos.y as i32;
+    framebuffer.set_pixel(px, py, Color::WHITE);
+    let num_rays = 20;
+    for i in 0..num_rays {
+        let current_ray = i as f32 / num_rays as f32;
+        let a = (player.a - (player.fov / 2.0)) + (player.fov * current_ray);
+        cast_ray(framebuffer, &maze, &player, a, block_size, true, ray_limits);
+    }
+}
+
+
+fn shade(color: Color, brightness: f32) -> Color {
+    Color::new((color.r as f32 * brightness) as u8, (color.g as f32 * brightness) as u8, (color.b as f32 * brightness) as u8, 255)
+}
+
+pub fn render_floor_ceiling( //Piso y techo con perspectiva, fila por fila; se dibuja antes que las paredes
+    framebuffer: &mut Framebuffer,
+    materials: &Materials,
+    block_size: usize,
+    player: &Player,
+    ray_limits: &RayLimits,
+    texture_cache: &TextureManager,
+    flashlight_radius: f32,
+) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let hh = height as f32 / 2.0;
+    let bs = block_size as f32;
+    let screen_center_x = width as f32 / 2.0;
+    // Dirección de cada columna dividida entre el coseno del desvío, así la distancia de la
+    // fila es perpendicular a la cámara igual que en las paredes y no hay ojo de pez
+    let directions: Vec<Vector2> = (0..width).map(|i| {
+        let a = (player.a - (player.fov / 2.0)) + (player.fov * (i as f32 / width as f32));
+        let correction = (a - player.a).cos();
+        Vector2::new(a.cos() / correction, a.sin() / correction)
+    }).collect();
+
+    let rows: Vec<_> = (hh as i32 + 1..height).into_par_iter().map(|y| {
+        let mut row_pixels = Vec::new();
+        // Una pared a distancia d mide 120*hh/d (ver render_3d): su base cae en esta fila
+        let dy = y as f32 - hh;
+        let row_distance = 60.0 * hh / dy;
+        if row_distance > ray_limits.max_distance {
+            return row_pixels;
+        }
+        let distance_fade = (1.0 - (row_distance / 1000.0)).max(0.0);
+        // Celdas que cubre un pixel de esta fila, para elegir el mipmap
+        let footprint = row_distance * player.fov / width as f32 / bs;
+        let sample = |key: char, u: f32, v: f32| {
+            let mips = texture_cache.get(key);
+            mips.level(footprint * mips.base().width() as f32).sample(u, v, FLOOR_FILTER, Wrap::Repeat)
+        };
+        let ceiling_y = height - 1 - y;
+        for x in 0..width {
+            let dist_from_center = ((x as f32 - screen_center_x).powi(2) + dy.powi(2)).sqrt();
+            if dist_from_center >= flashlight_radius { continue; } // fuera de la linterna queda negro
+            let falloff = 1.0 - (dist_from_center / flashlight_radius);
+            let brightness = falloff * falloff * distance_fade;
+            let world = player.pos + directions[x as usize] * row_distance;
+            let cell = NavGrid::cell_of(world, block_size);
+            let (u, v) = (world.x / bs - cell.0 as f32, world.y / bs - cell.1 as f32);
+            let floor = materials.floor(cell).map_or(FLOOR_COLOR, |key| sample(key, u, v));
+            row_pixels.push((x, y, shade(floor, brightness)));
+            if let Some(key) = materials.ceiling(cell) {
+                row_pixels.push((x, ceiling_y, shade(sample(key, u, v), brightness)));
+            }
+        }
+        row_pixels
+    }).collect();
+
+    for (x, y, color) in rows.into_iter().flatten() {
+        framebuffer.set_pixel(x, y, color);
+    }
+}
+
+pub fn render_3d( //Renderiza el laberinto en 3D
+    framebuffer: &mut Framebuffer,
+    maze: &Maze,
+    block_size: usize,
+    player: &Player,
+    ray_limits: &RayLimits,
+    texture_cache: &TextureManager,
+    flashlight_radius: f32,
+) {
+    let num_rays = framebuffer.width;
+    let hh = framebuffer.height as f32/ 2.0;
+    let screen_width = framebuffer.width as f32;
+    let screen_center_x = screen_width / 2.0;
+    let screen_center_y = hh;
+
+    let columns: Vec<_> = (0..num_rays).into_par_iter().map(|i| {
+        let current_ray = i as f32 / num_rays as f32;
+        let a = (player.a - (player.fov / 2.0)) + (player.fov * current_ray);
+        let angle_diff = a - player.a;
+        let mut column_pixels = Vec::new();
+        let intersect = match cast_grid(maze, player.pos, a, block_size, ray_limits) {
+            Some(intersect) => intersect,
+            None => {
+                // El rayo salió del nivel o pasó la distancia máxima: se dibuja cielo/niebla
+                for y in 0..hh as usize {
+                    let dist_from_center = ((i as f32 - screen_center_x).powi(2) + (y as f32 - screen_center_y).powi(2)).sqrt();
+                    let brightness = if dist_from_center < flashlight_radius { 1.0 - (dist_from_center / flashlight_radius) } else { 0.0 };
+                    let horizon = y as f32 / hh; // más claro cerca del horizonte
+                    let final_brightness = (0.3 + 0.7 * brightness) * horizon;
+                    column_pixels.push(Color::new(
+                        (FOG_COLOR.r as f32 * final_brightness) as u8,
+                        (FOG_COLOR.g as f32 * final_brightness) as u8,
+                        (FOG_COLOR.b as f32 * final_brightness) as u8,
+                        255,
+                    ));
+                }
+                return (i, 0, column_pixels);
+            }
+        };
+        let d = intersect.distance;
+        let c = intersect.impact;
+        let corrected_distance = d * angle_diff.cos() as f32;
+        let stake_height = (hh / corrected_distance)*120.0; //factor de escala rendering
+        let half_stake_height = stake_height / 2.0;
+        // Solo se recorre la parte visible; `v` se mide desde el borde real para no aplastar la textura
+        let stake_start = hh - half_stake_height;
+        let stake_top = stake_start.max(0.0) as usize;
+        let stake_bottom = (hh + half_stake_height).min(hh * 2.0) as usize;
+        let mips = texture_cache.get(c);
+        let texture = mips.level(mips.base().height() as f32 / stake_height);
+
+        for y in stake_top..stake_bottom {
+            let v = (y as f32 - stake_start) / stake_height;
+            let color = texture.sample(intersect.wall_x, v, WALL_FILTER, Wrap::Clamp);
+            let dist_from_center = ((i as f32 - screen_center_x).powi(2) + (y as f32 - screen_center_y).powi(2)).sqrt();
+            let flashlight_brightness = if dist_from_center < flashlight_radius {
+                let falloff = 1.0 - (dist_from_center / flashlight_radius);
+                falloff * falloff
+            } else { 0.0 };
+            let distance_fade = (1.0 - (corrected_distance / 1000.0)).max(0.0);
+            let final_brightness = flashlight_brightness * distance_fade;
+            let final_color = Color::new(
+                (color.r as f32 * final_brightness) as u8,
+                (color.g as f32 * final_brightness) as u8,
+                (color.b as f32 * final_brightness) as u8,
+                color.a
+            );
+            column_pixels.push(final_color);
+        }
+        (i, stake_top as i32, column_pixels)
+    }).collect();
+
+    for (i, y_start, column_pixels) in columns {
+        framebuffer.write_column(i, y_start, &column_pixels);
+    }
+}
+
+pub fn render_minimap(
+    framebuffer: &mut Framebuffer,
+    maze: &Maze,
+    player: &Player,
+    block_size: usize,
+    window_width: i32,
+    debug: Option<(&[Enemy], &NoiseTrail)>, // con F3: conos de visión, estados y ruidos
+) {
+    const MINIMAP_SCALE: f32 = 0.15;
+    let map_width = (maze.width() as f32 * block_size as f32 * MINIMAP_SCALE) as i32;
+    const BORDER_OFFSET: i32 = 10;
+    let offset_x = window_width - map_width - BORDER_OFFSET;
+    let offset_y = BORDER_OFFSET;
+    for (j, row) in maze.rows().enumerate() {
+        for (i, &cell) in row.iter().enumerate() {
+            if !cell.is_open() {
+                let rect_x = offset_x + (i as f32 * block_size as f32 * MINIMAP_SCALE) as i32;
+                let rect_y = offset_y + (j as f32 * block_size as f32 * MINIMAP_SCALE) as i32;
+                let rect_w = (block_size as f32 * MINIMAP_SCALE) as i32;
+                let rect_h = (block_size as f32 * MINIMAP_SCALE) as i32;
+                for y_offset in 0..rect_h {
+                    for x_offset in 0..rect_w {
+                        framebuffer.set_pixel(rect_x + x_offset, rect_y + y_offset, Color::new(100, 100, 100, 180));
+                    }
+                }
+            }
+        }
+    }
+    let player_map_x = offset_x + (player.pos.x * MINIMAP_SCALE) as i32;
+    let player_map_y = offset_y + (player.pos.y * MINIMAP_SCALE) as i32;
+    for dy in -2..=2 {
+        for dx in -2..=2 {
+            framebuffer.set_pixel(player_map_x + dx, player_map_y + dy, Color::YELLOW);
+        }
+    }
+    let line_length = 15.0;
+    let end_x = player_map_x as f32 + line_length * player.a.cos();
+    let end_y = player_map_y as f32 + line_length * player.a.sin();
+    for i in 0..15 {
+        let t = i as f32 / 14.0;
+        let x = player_map_x as f32 * (1.0 - t) + end_x * t;
+        let y = player_map_y as f32 * (1.0 - t) + end_y * t;
+        framebuffer.set_pixel(x as i32, y as i32, Color::YELLOW);
+    }
+
+    let Some((enemies, noise_trail)) = debug else { return };
+    let to_map = |pos: Vector2| Vector2::new(offset_x as f32 + pos.x * MINIMAP_SCALE, offset_y as f32 + pos.y * MINIMAP_SCALE);
+    for (noise, _) in &noise_trail.noises {
+        let center = to_map(noise.pos);
+        let radius = noise.radius * block_size as f32 * MINIMAP_SCALE;
+        for k in 0..48 {
+            let t = k as f32 / 48.0 * 2.0 * PI;
+            framebuffer.set_pixel((center.x + radius * t.cos()) as i32, (center.y + radius * t.sin()) as i32, Color::SKYBLUE);
+        }
+    }
+    for enemy in enemies {
+        let color = match enemy.state {
+            EnemyState::Patrol => Color::GREEN,
+            EnemyState::Chase => Color::RED,
+            EnemyState::Search => Color::ORANGE,
+            EnemyState::Return => Color::BLUE,
+        };
+        let center = to_map(enemy.pos);
+        for dy in -2..=2 {
+            for dx in -2..=2 {
+                framebuffer.set_pixel(center.x as i32 + dx, center.y as i32 + dy, color);
+            }
+        }
+        // Bordes del cono de visión
+        let params = enemy.params();
+        let facing = enemy.velocity().y.atan2(enemy.velocity().x);
+        let length = params.sight * block_size as f32 * MINIMAP_SCALE;
+        for edge in [facing - params.fov / 2.0, facing + params.fov / 2.0] {
+            for k in 0..length as i32 {
+                let d = k as f32;
+                framebuffer.set_pixel((center.x + d * edge.cos()) as i32, (center.y + d * edge.sin()) as i32, color);
+            }
+        }
+    }
+}
+
+// Dibuja la vista inicial de un nivel (piso, paredes, sprites y minimapa) a un PPM, sin
+// abrir ventana: sirve para revisar el render en máquinas sin pantalla
+pub fn run_cli(args: &[String]) -> i32 {
+    let (Some(path), Some(out)) = (args.first(), args.get(1)) else {
+        eprintln!("uso: render <nivel> <salida.ppm>");
+        return 2;
+    };
+    let level = match Level::load(path) {
+        Ok(level) => level,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let (texture_cache, texture_warnings) = match TextureManager::load(TEXTURES_FILE) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    for warning in &texture_warnings {
+        eprintln!("Textura: {}", warning);
+    }
+    let block_size = 100;
+    let ray_limits = RayLimits::default();
+    let g = Game::new(level, block_size, 0);
+    let (width, height) = (1300, 900); // mismo tamaño que la ventana
+    let mut framebuffer = Framebuffer::new(width, height, Color::BLACK);
+    render_floor_ceiling(&mut framebuffer, &g.level.materials, block_size, &g.player, &ray_limits, &texture_cache, FLASHLIGHT_RADIUS);
+    render_3d(&mut framebuffer, &g.level.maze, block_size, &g.player, &ray_limits, &texture_cache, FLASHLIGHT_RADIUS);
+    render_enemies(&mut framebuffer, &g.player, &g.enemies, 1.0, &texture_cache, FLASHLIGHT_RADIUS);
+    render_collectables(&mut framebuffer, &g.player, &g.collectables, &texture_cache, FLASHLIGHT_RADIUS);
+    render_minimap(&mut framebuffer, &g.level.maze, &g.player, block_size, width, None);
+    match framebuffer.save_ppm(out) {
+        Ok(()) => {
+            println!("{}x{} guardado en {}", width, height, out);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {}", out, e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{RaggedRows, parse_maze};
+
+    #[test]
+    fn corridor_draws_wall_at_horizon_and_floor_below() {
+        // Pasillo de 5 celdas; el jugador en la primera mira al este, a 450px de la pared
+        let (maze, _) = parse_maze("+--+--+\n|     |\n+--+--+", RaggedRows::Reject);
+        let player = Player::new(Vector2::new(150.0, 150.0), 0.0, PI / 3.0);
+        // Sin manifiesto todas las llaves usan el tablero magenta y negro
+        let textures = TextureManager::fallback();
+        let limits = RayLimits::default();
+        let mut framebuffer = Framebuffer::new(64, 48, Color::BLACK);
+        render_floor_ceiling(&mut framebuffer, &Materials::default(), 100, &player, &limits, &textures, 1000.0);
+        render_3d(&mut framebuffer, &maze, 100, &player, &limits, &textures, 1000.0);
+
+        let column: Vec<Color> = (0..48).map(|y| framebuffer.get_pixel_color(32, y).unwrap()).collect();
+        // La pared mide 120 * 24 / 450 ≈ 6 filas alrededor del horizonte
+        assert!(column[21..=27].iter().any(|c| c.r > 0 && c.g == 0 && c.b > 0), "pared: {:?}", &column[21..=27]);
+        let floor = column[47];
+        assert!(floor.r > floor.g && floor.g > 0 && floor.b == 0, "piso: {:?}", floor);
+        // Sin textura de techo arriba queda el fondo
+        assert_eq!((column[0].r, column[0].g, column[0].b), (0, 0, 0));
+    }
+}
@@ -0,0 +1,256 @@
+// replay.rs
+//
+// Grabación de partidas: se guarda el nivel, la semilla, el tamaño de bloque y la entrada +
+// delta time de cada paso, y al final el hash del estado. Al repetirla se pasa todo por `Game::step` otra vez
+// y el hash final tiene que coincidir.
+//
+// Formato binario (little endian):
+//   "MBIR" | versión u8 | tipo de nivel u8 (0 archivo, 1 infinito)
+//   archivo: largo u16 + ruta utf-8 | infinito: semilla u64 + profundidad u32
+//   semilla u64 | block_size u32 | cantidad de pasos u32
+//   por paso: flags u8, forward f32, strafe f32, look_delta f32 (radianes), dt f32
+//   hash final u64
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::game::{Game, InputFrame};
+use crate::level::{LevelError, LevelSource};
+
+const MAGIC: &[u8; 4] = b"MBIR";
+const VERSION: u8 = 7; // 3: look_delta en radianes, 4: block_size en el encabezado, 5: vida, vidas y checkpoint en el hash, 6: centro de las celdas generadas, 7: movimiento analógico
+
+pub struct Recording {
+    pub level: LevelSource,
+    pub seed: u64,
+    pub block_size: usize, // la simulación depende de él, así que se repite con el mismo
+    pub frames: Vec<(InputFrame, f32)>,
+    pub final_hash: u64,
+}
+
+impl Recording {
+    pub fn new(level: LevelSource, seed: u64, block_size: usize) -> Self {
+        Recording { level, seed, block_size, frames: Vec::new(), final_hash: 0 }
+    }
+
+    pub fn push(&mut self, input: InputFrame, dt: f32) {
+        self.frames.push((input, dt));
+    }
+
+    // Vuelve a correr todos los pasos sobre el nivel grabado
+    pub fn play(&self) -> Result<Game, LevelError> {
+        let mut game = Game::new(self.level.load()?, self.block_size, self.seed);
+        for &(input, dt) in &self.frames {
+            game.step(input, dt);
+        }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        match &self.level {
+            LevelSource::File(file) => {
+                let file = file.to_string_lossy();
+                out.write_all(&[0])?;
+                out.write_all(&(file.len() as u16).to_le_bytes())?;
+                out.write_all(file.as_bytes())?;
+            }
+            LevelSource::Endless { seed, depth } => {
+                out.write_all(&[1])?;
+                out.write_all(&seed.to_le_bytes())?;
+                out.write_all(&depth.to_le_bytes())?;
+            }
+        }
+        out.write_all(&self.seed.to_le_bytes())?;
+        out.write_all(&(self.block_size as u32).to_le_bytes())?;
+        out.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for (input, dt) in &self.frames {
+            out.write_all(&[pack_flags(input)])?;
+            out.write_all(&input.forward.to_le_bytes())?;
+            out.write_all(&input.strafe.to_le_bytes())?;
+            out.write_all(&input.look_delta.to_le_bytes())?;
+            out.write_all(&dt.to_le_bytes())?;
+        }
+        out.write_all(&self.final_hash.to_le_bytes())?;
+        out.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "no es una repetición"));
+        }
+        let version = read_u8(&mut input)?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("versión {} no soportada", version)));
+        }
+        let level = match read_u8(&mut input)? {
+            0 => {
+                let mut len = [0u8; 2];
+                input.read_exact(&mut len)?;
+                let mut bytes = vec![0u8; u16::from_le_bytes(len) as usize];
+                input.read_exact(&mut bytes)?;
+                let file = String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                LevelSource::File(PathBuf::from(file))
+            }
+            1 => LevelSource::Endless { seed: read_u64(&mut input)?, depth: read_u32(&mut input)? },
+            other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("tipo de nivel {} desconocido", other))),
+        };
+        let seed = read_u64(&mut input)?;
+        let block_size = read_u32(&mut input)? as usize;
+        if block_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "block_size 0"));
+        }
+        let count = read_u32(&mut input)? as usize;
+        let mut frames = Vec::with_capacity(count);
+        for _ in 0..count {
+            let flags = read_u8(&mut input)?;
+            let mut frame = unpack_flags(flags);
+            frame.forward = read_f32(&mut input)?;
+            frame.strafe = read_f32(&mut input)?;
+            frame.look_delta = read_f32(&mut input)?;
+            let dt = read_f32(&mut input)?;
+            frames.push((frame, dt));
+        }
+        let final_hash = read_u64(&mut input)?;
+        Ok(Recording { level, seed, block_size, frames, final_hash })
+    }
+}
+
+fn pack_flags(input: &InputFrame) -> u8 {
+    (input.turn_left as u8)
+        | ((input.turn_right as u8) << 1)
+        | ((input.sprint as u8) << 2)
+        | ((input.crouch as u8) << 3)
+}
+
+fn unpack_flags(flags: u8) -> InputFrame {
+    InputFrame {
+        turn_left: flags & 1 != 0,
+        turn_right: flags & (1 << 1) != 0,
+        sprint: flags & (1 << 2) != 0,
+        crouch: flags & (1 << 3) != 0,
+        ..InputFrame::default()
+    }
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f32(input: &mut impl Read) -> io::Result<f32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+// Subcomando `replay`: vuelve a correr la grabación sin ventana y compara el hash final
+pub fn run_cli(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("uso: replay <archivo.replay>");
+        return 2;
+    };
+    let recording = match Recording::load(path) {
+        Ok(recording) => recording,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 1;
+        }
+    };
+    let game = match recording.play() {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let hash = game.state_hash();
+    println!("{} pasos, estado final {:?}, puntos {}/{}", recording.frames.len(), game.state, game.score, game.max_score());
+    if hash == recording.final_hash {
+        println!("ok: hash {:016x}", hash);
+        0
+    } else {
+        println!("DIFERENTE: grabado {:016x}, repetido {:016x}", recording.final_hash, hash);
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.replay", name, std::process::id()))
+    }
+
+    #[test]
+    fn saved_recording_replays_to_the_same_hash() {
+        let source = LevelSource::File(PathBuf::from("levels/easy.level"));
+        let mut recording = Recording::new(source.clone(), 42, 100);
+        let mut game = Game::new(source.load().unwrap(), 100, 42);
+        // Entrada fija: avanza, gira, corre y se agacha por tramos
+        for tick in 0..600 {
+            let input = InputFrame {
+                // Medio stick hacia adelante en parte del tramo, como con un control
+                forward: if tick % 200 < 100 { 1.0 } else if tick % 200 < 150 { 0.5 } else { 0.0 },
+                turn_left: tick % 90 < 20,
+                strafe: if tick % 300 > 250 { 1.0 } else { 0.0 },
+                sprint: tick % 400 < 100,
+                crouch: tick % 500 > 450,
+                look_delta: if tick % 50 == 0 { 0.1 } else { 0.0 },
+                ..InputFrame::default()
+            };
+            recording.push(input, 1.0 / 60.0);
+            game.step(input, 1.0 / 60.0);
+        }
+        recording.final_hash = game.state_hash();
+
+        let path = temp_file("roundtrip");
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!((loaded.seed, loaded.block_size, loaded.frames.len()), (42, 100, 600));
+        assert_eq!(loaded.final_hash, recording.final_hash);
+        assert_eq!(loaded.play().unwrap().state_hash(), recording.final_hash);
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let path = temp_file("magic");
+        fs::write(&path, b"NOPE\x05").unwrap();
+        let result = Recording::load(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(result.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn old_version_is_rejected() {
+        let path = temp_file("version");
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION - 1);
+        fs::write(&path, bytes).unwrap();
+        let result = Recording::load(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(result.err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+    }
+}
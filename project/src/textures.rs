@@ -1,89 +1,409 @@
 // textures.rs
+//
+// Registro de texturas definido en `assets/textures.cfg`. Cada sección lista una o más
+// llaves de un caracter (las que usan el laberinto, los niveles y los enemigos):
+//
+//   [+ - | #]
+//   image = wall.png           relativa a la carpeta del manifiesto
+//   frames = 1                 cuadros de animación lado a lado en la imagen
+//   frame_time = 0.2           segundos por cuadro
+//   scale = 1                  tamaño de los sprites (1 = normal)
+//   transparent = 255 0 255    color que se vuelve transparente (opcional)
+//
+// Solo son comentarios las líneas que empiezan con `#`, así `#` puede ser una llave.
+// Un manifiesto mal escrito es un error con su línea y columna; si lo que falla es cargar
+// una imagen solo se avisa y sus llaves usan un tablero de respaldo.
+//
+// Al cargar, cada cuadro se copia a un `Texels` propio (RGBA empaquetado en u32), así
+// leer un texel es un índice en un Vec y el registro se comparte entre hilos sin `unsafe`.
+// De cada cuadro se arma también su cadena de mipmaps para dibujarlo lejos sin que titile.
 use raylib::prelude::*;
 use std::collections::HashMap;
-use std::slice;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::level::{LevelError, first_column, tokens};
 
-pub struct TextureManager {
-    image_data: HashMap<char, (Image, u32, u32)>, // Store images, width, and height for pixel access
-    textures: HashMap<char, Texture2D>, // Store GPU textures for rendering
+pub const TEXTURES_FILE: &str = "assets/textures.cfg";
+const FALLBACK: usize = 0; // índice del tablero en `textures`
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Nearest,
+    Bilinear, // mezcla los cuatro texels vecinos, se ve mejor de cerca
 }
 
-unsafe impl Sync for TextureManager {}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wrap {
+    Repeat, // la textura se repite (piso y techo)
+    Clamp, // se queda en el borde (paredes y sprites)
+}
 
-impl TextureManager {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread) -> Self {
-        let mut image_data = HashMap::new();
-        let mut textures = HashMap::new();
-
-        // Map characters to texture file paths
-        let texture_files = vec![
-            ('+', "assets/wall.png"),
-            ('-', "assets/wall.png"),
-            ('|', "assets/wall.png"),
-            ('g', "assets/reggie.png"),
-            ('e', "assets/reggie.png"),
-            ('f', "assets/wii.png"),
-            ('c', "assets/wii.png"),
-            ('h', "assets/wii.png"),
-            ('n', "assets/nintendo.png"), // Added nintendo.png
-            ('d', "assets/wite_nintendo_direct.png"), // Added wite_nintendo_direct.png
-            ('#', "assets/wall.png"), // default/fallback
-        ];
-
-        for (ch, path) in texture_files {
-            let image = Image::load_image(path).expect(&format!("Failed to load image {}", path));
-            let width = image.width as u32;
-            let height = image.height as u32;
-            let texture = rl.load_texture(thread, path).expect(&format!("Failed to load texture {}", path));
-            texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_TRILINEAR);
-            image_data.insert(ch, (image, width, height));
-            textures.insert(ch, texture);
+pub struct Texels { //Pixeles RGBA de una textura, `r` en el byte más bajo
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+}
+
+impl Texels {
+    fn from_image(image: &Image) -> Self {
+        // raylib convierte cualquier formato de pixel a Color al copiar
+        let pixels = image.get_image_data().iter().map(|c| u32::from_le_bytes([c.r, c.g, c.b, c.a])).collect();
+        Texels { width: image.width as u32, height: image.height as u32, pixels }
+    }
+
+    fn checkerboard(size: u32, cell: u32) -> Self {
+        let magenta = u32::from_le_bytes([255, 0, 255, 255]);
+        let black = u32::from_le_bytes([0, 0, 0, 255]);
+        let pixels = (0..size * size)
+            .map(|i| if ((i % size) / cell + (i / size) / cell) % 2 == 0 { magenta } else { black })
+            .collect();
+        Texels { width: size, height: size, pixels }
+    }
+
+    // Corta una tira horizontal en `count` cuadros del mismo ancho
+    fn frames(&self, count: u32) -> Vec<Texels> {
+        let frame_width = self.width / count;
+        (0..count).map(|frame| {
+            let pixels = (0..self.height)
+                .flat_map(|y| {
+                    let start = (y * self.width + frame * frame_width) as usize;
+                    self.pixels[start..start + frame_width as usize].iter().copied()
+                })
+                .collect();
+            Texels { width: frame_width, height: self.height, pixels }
+        }).collect()
+    }
+
+    // Nivel de mipmap siguiente: cada texel es el promedio de 2x2, pesado por el alfa para
+    // que los bordes transparentes de los sprites no se oscurezcan
+    fn half(&self) -> Texels {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let texels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(dx, dy)| self.texel(2 * x + dx, 2 * y + dy, Wrap::Clamp).to_le_bytes());
+                let alpha: u32 = texels.iter().map(|t| t[3] as u32).sum();
+                let channel = |i: usize| match alpha {
+                    0 => 0,
+                    _ => (texels.iter().map(|t| t[i] as u32 * t[3] as u32).sum::<u32>() / alpha) as u8,
+                };
+                pixels.push(u32::from_le_bytes([channel(0), channel(1), channel(2), (alpha / 4) as u8]));
+            }
         }
+        Texels { width, height, pixels }
+    }
 
-        TextureManager { image_data, textures }
+    pub fn width(&self) -> u32 {
+        self.width
     }
 
-    pub fn get_pixel_color(&self, ch: char, tx: u32, ty: u32) -> Color {
-        if let Some((image, width, height)) = self.image_data.get(&ch) {
-            let x = tx.min(width - 1) as i32;
-            let y = ty.min(height - 1) as i32;
-            get_pixel_color(image, x, y)
-        } else {
-            Color::WHITE
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn texel(&self, x: i32, y: i32, wrap: Wrap) -> u32 {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (x, y) = match wrap {
+            Wrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            Wrap::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+        };
+        self.pixels[(y * width + x) as usize]
+    }
+
+    // `u` y `v` van de 0 a 1 sobre toda la textura
+    #[inline]
+    pub fn sample(&self, u: f32, v: f32, filter: Filter, wrap: Wrap) -> Color {
+        let x = u * self.width as f32;
+        let y = v * self.height as f32;
+        match filter {
+            Filter::Nearest => {
+                let [r, g, b, a] = self.texel(x.floor() as i32, y.floor() as i32, wrap).to_le_bytes();
+                Color::new(r, g, b, a)
+            }
+            Filter::Bilinear => {
+                // Centros de texel en .5: se mezclan los cuatro que rodean al punto
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+                let corners = [
+                    self.texel(x0, y0, wrap),
+                    self.texel(x0 + 1, y0, wrap),
+                    self.texel(x0, y0 + 1, wrap),
+                    self.texel(x0 + 1, y0 + 1, wrap),
+                ].map(u32::to_le_bytes);
+                let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+                let channel = |i: usize| corners.iter().zip(weights).map(|(texel, w)| texel[i] as f32 * w).sum::<f32>().round() as u8;
+                Color::new(channel(0), channel(1), channel(2), channel(3))
+            }
         }
     }
+}
+
+pub struct Mips { //Cadena de mipmaps: cada nivel mide la mitad del anterior, hasta 1x1
+    levels: Vec<Texels>,
+}
 
-    pub fn get_texture(&self, ch: char) -> Option<&Texture2D> {
-        self.textures.get(&ch)
+impl Mips {
+    fn new(base: Texels) -> Self {
+        let mut levels = vec![base];
+        while let Some(last) = levels.last().filter(|t| t.width > 1 || t.height > 1) {
+            let next = last.half();
+            levels.push(next);
+        }
+        Mips { levels }
     }
 
-    pub fn get_image_dimensions(&self, ch: char) -> Option<(u32, u32)> {
-        self.image_data.get(&ch).map(|(_, w, h)| (*w, *h))
+    pub fn base(&self) -> &Texels {
+        &self.levels[0]
     }
+
+    // Nivel para cuando un pixel de pantalla cubre `texels_per_pixel` texels de la base
+    pub fn level(&self, texels_per_pixel: f32) -> &Texels {
+        let lod = if texels_per_pixel > 1.0 { texels_per_pixel.log2() as usize } else { 0 };
+        &self.levels[lod.min(self.levels.len() - 1)]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct TextureInfo {
+    first: usize, // índice del primer cuadro en `textures`; varias llaves pueden compartirlo
+    frames: u32,
+    frame_time: f32,
+    scale: f32,
+}
+
+impl Default for TextureInfo {
+    fn default() -> Self {
+        TextureInfo { first: FALLBACK, frames: 1, frame_time: 0.2, scale: 1.0 }
+    }
+}
+
+struct Section { //Sección del manifiesto ya leída
+    line: usize,
+    keys: Vec<char>,
+    image: String,
+    transparent: Option<[u8; 3]>,
+    info: TextureInfo,
+}
+
+// Imágenes ya cargadas: (archivo, color transparente, cuadros) -> (primer cuadro, cuadros)
+type Loaded = HashMap<(PathBuf, Option<[u8; 3]>, u32), (usize, u32)>;
+
+pub struct TextureManager {
+    textures: Vec<Mips>, // todos los cuadros de todas las imágenes
+    entries: HashMap<char, TextureInfo>,
+    time: f32, // reloj de las animaciones
 }
 
-fn get_pixel_color(image: &Image, x: i32, y: i32) -> Color {
-    let width = image.width as usize;
-    let height = image.height as usize;
+impl TextureManager {
+    // Registro vacío: todas las llaves usan el tablero
+    pub fn fallback() -> Self {
+        TextureManager { textures: vec![Mips::new(Texels::checkerboard(64, 8))], entries: HashMap::new(), time: 0.0 }
+    }
+
+    // Falla si el manifiesto no se puede leer o está mal escrito; los avisos son de las
+    // imágenes que no se pudieron cargar
+    pub fn load(path: impl AsRef<Path>) -> Result<(Self, Vec<String>), LevelError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| LevelError::io(path, e))?;
+        let sections = parse_manifest(&source, &path.display().to_string())?;
+        let mut manager = TextureManager::fallback();
+        let mut warnings = Vec::new();
+        let mut loaded = Loaded::new();
+        for section in sections {
+            manager.add(section, path, &mut loaded, &mut warnings);
+        }
+        Ok((manager, warnings))
+    }
+
+    // Carga la imagen de la sección (una sola vez por archivo, color transparente y cuadros)
+    // y registra sus llaves; si algo falla las llaves quedan con el tablero
+    fn add(&mut self, section: Section, manifest: &Path, loaded: &mut Loaded, warnings: &mut Vec<String>) {
+        let base_dir = manifest.parent().unwrap_or(Path::new("."));
+        let mut info = section.info;
+        let mut warn = |message: String| warnings.push(format!("{}:{}: {}", manifest.display(), section.line, message));
+        let image_path = base_dir.join(&section.image);
+        let cache_key = (image_path.clone(), section.transparent, info.frames);
+        if let Some(&(first, frames)) = loaded.get(&cache_key) {
+            (info.first, info.frames) = (first, frames);
+        } else {
+            match Image::load_image(&image_path.display().to_string()) {
+                Ok(mut image) => {
+                    image.set_format(PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8);
+                    if let Some([r, g, b]) = section.transparent {
+                        image.color_replace(Color::new(r, g, b, 255), Color::new(0, 0, 0, 0));
+                    }
+                    let strip = Texels::from_image(&image);
+                    if info.frames > strip.width {
+                        warn(format!("{} cuadros no caben en una imagen de {} pixeles de ancho", info.frames, strip.width));
+                        info.frames = 1;
+                    }
+                    info.first = self.textures.len();
+                    self.textures.extend(strip.frames(info.frames).into_iter().map(Mips::new));
+                    loaded.insert(cache_key, (info.first, info.frames));
+                }
+                Err(e) => warn(format!("no se pudo cargar {}: {}", image_path.display(), e)),
+            }
+        }
+        if info.first == FALLBACK {
+            info.frames = 1;
+        }
+        for &key in &section.keys {
+            self.entries.insert(key, info);
+        }
+    }
 
-    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
-        return Color::WHITE;
+    // Avanza el reloj de las texturas animadas; se llama una vez por frame
+    pub fn animate(&mut self, dt: f32) {
+        self.time += dt;
     }
 
-    let x = x as usize;
-    let y = y as usize;
+    fn info(&self, ch: char) -> TextureInfo {
+        self.entries.get(&ch).copied().unwrap_or_default()
+    }
 
-    let data_len = width * height * 4;
+    // Cuadro actual de la llave (las llaves sin textura usan el tablero)
+    pub fn get(&self, ch: char) -> &Mips {
+        let info = self.info(ch);
+        let frame = (self.time / info.frame_time) as usize % info.frames as usize;
+        &self.textures[info.first + frame]
+    }
 
-    unsafe {
-        let data = slice::from_raw_parts(image.data as *const u8, data_len);
+    pub fn scale(&self, ch: char) -> f32 {
+        self.info(ch).scale
+    }
+}
+
+fn parse_manifest(source: &str, path: &str) -> Result<Vec<Section>, LevelError> {
+    let mut sections = Vec::new();
+    let mut current: Option<(Section, usize)> = None; // con la columna del `[`, por si le falta `image`
+    // Una sección sin `image` se reporta donde empieza
+    let finish = |current: Option<(Section, usize)>, sections: &mut Vec<Section>| -> Result<(), LevelError> {
+        let Some((section, column)) = current else { return Ok(()) };
+        if section.image.is_empty() {
+            return Err(LevelError::Parse { path: path.to_string(), line: section.line, column, message: "la sección no tiene `image`".to_string() });
+        }
+        sections.push(section);
+        Ok(())
+    };
 
-        let idx = (y * width + x) * 4;
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+        let error = |column: usize, message: String| LevelError::Parse { path: path.to_string(), line: index + 1, column, message };
+        let start = first_column(line);
 
-        if idx + 3 >= data_len {
-            return Color::WHITE;
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            finish(current.take(), &mut sections)?;
+            // Las llaves van entre el `[` (columna `start`) y el último `]`
+            let close = line.rfind(']').unwrap_or(line.len());
+            let mut keys = Vec::new();
+            for (column, key) in tokens(&line[..close], start) {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => keys.push(c),
+                    _ => return Err(error(column, format!("la llave `{}` debe ser un solo caracter", key))),
+                }
+            }
+            if keys.is_empty() {
+                return Err(error(start, "la sección no tiene llaves".to_string()));
+            }
+            let section = Section { line: index + 1, keys, image: String::new(), transparent: None, info: TextureInfo::default() };
+            current = Some((section, start));
+            continue;
         }
 
-        Color::new(data[idx], data[idx + 1], data[idx + 2], data[idx + 3])
+        let Some(equals) = line.find('=') else {
+            return Err(error(start, "se esperaba `[llaves]` o `clave = valor`".to_string()));
+        };
+        let key = line[..equals].trim();
+        let value = line[equals + 1..].trim();
+        let value_column = tokens(line, equals + 1).first().map_or(line.len() + 1, |&(column, _)| column);
+        let Some((section, _)) = current.as_mut() else {
+            return Err(error(start, format!("`{}` antes de la primera sección [llaves]", key)));
+        };
+        match key {
+            "image" if value.is_empty() => return Err(error(value_column, "falta el archivo de `image`".to_string())),
+            "image" => section.image = value.to_string(),
+            "frames" => match value.parse::<u32>() {
+                Ok(frames) if frames > 0 => section.info.frames = frames,
+                _ => return Err(error(value_column, format!("`{}` debe ser un entero mayor que 0", value))),
+            },
+            "frame_time" | "scale" => match value.parse::<f32>() {
+                Ok(number) if number > 0.0 => {
+                    if key == "scale" { section.info.scale = number; } else { section.info.frame_time = number; }
+                }
+                _ => return Err(error(value_column, format!("`{}` debe ser un número mayor que 0", value))),
+            },
+            "transparent" => {
+                let rgb: Option<Vec<u8>> = value.split_whitespace().map(|c| c.parse().ok()).collect();
+                match rgb.as_deref() {
+                    Some(&[r, g, b]) => section.transparent = Some([r, g, b]),
+                    _ => return Err(error(value_column, format!("`{}` no es un color `r g b` (0 a 255)", value))),
+                }
+            }
+            _ => return Err(error(start, format!("clave `{}` desconocida", key))),
+        }
     }
-}
\ No newline at end of file
+    finish(current.take(), &mut sections)?;
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_at(source: &str) -> (usize, usize) {
+        match parse_manifest(source, "t") {
+            Err(LevelError::Parse { line, column, .. }) => (line, column),
+            Err(e) => panic!("error inesperado: {}", e),
+            Ok(_) => panic!("se esperaba un error"),
+        }
+    }
+
+    #[test]
+    fn shipped_manifest_parses() {
+        let source = fs::read_to_string(TEXTURES_FILE).unwrap();
+        let sections = parse_manifest(&source, TEXTURES_FILE).unwrap_or_else(|e| panic!("{}", e));
+        assert!(sections.iter().any(|s| s.keys.contains(&'#')), "`#` debe poder ser una llave");
+    }
+
+    #[test]
+    fn malformed_lines_report_line_and_column() {
+        assert_eq!(error_at("[a]\nimage = a.png\n  colour = red"), (3, 3));
+        assert_eq!(error_at("[a bc]\nimage = a.png"), (1, 4));
+        assert_eq!(error_at("[ ]"), (1, 1));
+        assert_eq!(error_at("image = a.png"), (1, 1));
+        assert_eq!(error_at("[a]\nimage a.png"), (2, 1));
+        assert_eq!(error_at("[a]\nimage = a.png\nframes = 0"), (3, 10));
+        assert_eq!(error_at("[a]\nimage = a.png\nscale = -1"), (3, 9));
+        assert_eq!(error_at("[a]\nimage = a.png\ntransparent = 255 0"), (3, 15));
+    }
+
+    #[test]
+    fn section_without_image_is_rejected() {
+        assert_eq!(error_at("[a]\nframes = 2\n  [b]\nimage = b.png"), (1, 1));
+        assert_eq!(error_at("[a]\nimage = a.png\n  [b]"), (3, 3));
+    }
+
+    #[test]
+    fn missing_manifest_is_an_error() {
+        assert!(matches!(TextureManager::load("no-existe.cfg"), Err(LevelError::Io { .. })));
+    }
+
+    #[test]
+    fn missing_image_falls_back_to_the_checkerboard() {
+        let dir = std::env::temp_dir().join(format!("textures-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("textures.cfg");
+        fs::write(&manifest, "[x]\nimage = no-existe.png\n").unwrap();
+        let (manager, warnings) = TextureManager::load(&manifest).unwrap_or_else(|e| panic!("{}", e));
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(std::ptr::eq(manager.get('x'), manager.get('?')), "`x` debería usar el tablero");
+    }
+}
@@ -0,0 +1,225 @@
+// validate.rs
+//
+// Revisa que un nivel se pueda ganar: el jugador, los coleccionables, los enemigos y la
+// salida tienen que estar en celdas abiertas y conectadas. Se usa desde la línea de
+// comandos (`cargo run -- validate levels/hard.level`) y desde las pruebas.
+use raylib::prelude::*;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::PathBuf;
+use crate::collectable::awards_point;
+use crate::level::{Level, discover_levels};
+use crate::maze::{Maze, Tile};
+
+pub enum Problem {
+    StartBlocked { pos: Vector2 },
+    ItemBlocked { index: usize, pos: Vector2 },
+    ItemUnreachable { index: usize, pos: Vector2 },
+    EnemyBlocked { index: usize, pos: Vector2 },
+    EnemyUnreachable { index: usize, pos: Vector2 },
+    PatrolUnreachable { enemy: usize, pos: Vector2 },
+    CheckpointUnreachable { index: usize, pos: Vector2 },
+    NoExit,
+    ExitUnreachable,
+    ScoreUnreachable { target: u32, available: u32 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::StartBlocked { pos } => write!(f, "el inicio ({}, {}) está dentro de una pared", pos.x, pos.y),
+            Problem::ItemBlocked { index, pos } => write!(f, "el item #{} ({}, {}) está dentro de una pared", index + 1, pos.x, pos.y),
+            Problem::ItemUnreachable { index, pos } => write!(f, "el item #{} ({}, {}) no se puede alcanzar", index + 1, pos.x, pos.y),
+            Problem::EnemyBlocked { index, pos } => write!(f, "el enemigo #{} ({}, {}) está dentro de una pared", index + 1, pos.x, pos.y),
+            Problem::EnemyUnreachable { index, pos } => write!(f, "el enemigo #{} ({}, {}) está encerrado lejos del jugador", index + 1, pos.x, pos.y),
+            Problem::PatrolUnreachable { enemy, pos } => write!(f, "el punto de patrulla ({}, {}) del enemigo #{} está en una pared o encerrado", pos.x, pos.y, enemy + 1),
+            Problem::CheckpointUnreachable { index, pos } => write!(f, "el checkpoint #{} ({}, {}) está en una pared o no se puede alcanzar", index + 1, pos.x, pos.y),
+            Problem::NoExit => write!(f, "el laberinto no tiene salida 'g'"),
+            Problem::ExitUnreachable => write!(f, "ninguna salida 'g' se puede alcanzar desde el inicio"),
+            Problem::ScoreUnreachable { target, available } => write!(f, "se necesitan {} puntos pero solo se pueden juntar {}", target, available),
+        }
+    }
+}
+
+pub struct Report {
+    pub route_length: Option<usize>, // celdas del camino más corto del inicio a la salida
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+// Celda (columna, fila) que contiene una posición dada en celdas
+fn cell_of(pos: Vector2) -> (i32, i32) {
+    (pos.x.floor() as i32, pos.y.floor() as i32)
+}
+
+// Distancia (BFS) desde `start` a cada celda abierta; None si no se puede llegar
+pub fn flood_fill(maze: &Maze, start: (i32, i32)) -> Vec<Vec<Option<usize>>> {
+    let mut distance = vec![vec![None; maze.width()]; maze.height()];
+    if maze.get(start.0, start.1) != Some(Tile::Empty) {
+        return distance;
+    }
+    let mut queue = VecDeque::new();
+    distance[start.1 as usize][start.0 as usize] = Some(0);
+    queue.push_back(start);
+    while let Some((i, j)) = queue.pop_front() {
+        let d = distance[j as usize][i as usize].unwrap_or(0);
+        for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (ni, nj) = (i + di, j + dj);
+            if maze.get(ni, nj) == Some(Tile::Empty) && distance[nj as usize][ni as usize].is_none() {
+                distance[nj as usize][ni as usize] = Some(d + 1);
+                queue.push_back((ni, nj));
+            }
+        }
+    }
+    distance
+}
+
+pub fn validate(level: &Level) -> Report {
+    let maze = &level.maze;
+    let mut problems = Vec::new();
+    let start = cell_of(level.start);
+    if maze.get(start.0, start.1) != Some(Tile::Empty) {
+        problems.push(Problem::StartBlocked { pos: level.start });
+    }
+    let distance = flood_fill(maze, start);
+    let reach = |cell: (i32, i32)| -> Option<usize> {
+        if cell.0 < 0 || cell.1 < 0 {
+            return None;
+        }
+        distance.get(cell.1 as usize).and_then(|row| row.get(cell.0 as usize)).copied().flatten()
+    };
+
+    let mut available = 0;
+    for (index, item) in level.collectables.iter().enumerate() {
+        let cell = cell_of(item.pos);
+        if maze.get(cell.0, cell.1) != Some(Tile::Empty) {
+            problems.push(Problem::ItemBlocked { index, pos: item.pos });
+        } else if reach(cell).is_none() {
+            problems.push(Problem::ItemUnreachable { index, pos: item.pos });
+        } else if awards_point(item.texture_key) {
+            available += 1;
+        }
+    }
+    if available < level.score_target {
+        problems.push(Problem::ScoreUnreachable { target: level.score_target, available });
+    }
+
+    for (index, enemy) in level.enemies.iter().enumerate() {
+        let cell = cell_of(enemy.pos);
+        if maze.get(cell.0, cell.1) != Some(Tile::Empty) {
+            problems.push(Problem::EnemyBlocked { index, pos: enemy.pos });
+        } else if reach(cell).is_none() {
+            problems.push(Problem::EnemyUnreachable { index, pos: enemy.pos });
+        }
+        for &pos in &enemy.waypoints {
+            if reach(cell_of(pos)).is_none() {
+                problems.push(Problem::PatrolUnreachable { enemy: index, pos });
+            }
+        }
+    }
+
+    for (index, &pos) in level.checkpoints.iter().enumerate() {
+        if reach(cell_of(pos)).is_none() {
+            problems.push(Problem::CheckpointUnreachable { index, pos });
+        }
+    }
+
+    // La salida se alcanza entrando desde cualquier celda abierta vecina
+    let mut has_exit = false;
+    let mut route_length: Option<usize> = None;
+    for (j, row) in maze.rows().enumerate() {
+        for (i, &tile) in row.iter().enumerate() {
+            if tile != Tile::Exit { continue; }
+            has_exit = true;
+            for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                if let Some(d) = reach((i as i32 + di, j as i32 + dj)) {
+                    route_length = Some(route_length.map_or(d + 1, |best| best.min(d + 1)));
+                }
+            }
+        }
+    }
+    if !has_exit {
+        problems.push(Problem::NoExit);
+    } else if route_length.is_none() {
+        problems.push(Problem::ExitUnreachable);
+    }
+
+    Report { route_length, problems }
+}
+
+// Subcomando `validate`: revisa los niveles dados (o todos los de `levels/`) y devuelve
+// el código de salida del proceso
+pub fn run_cli(paths: &[String]) -> i32 {
+    let mut failed = 0;
+    let mut paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    if paths.is_empty() {
+        // Los niveles que ni siquiera cargan ya vienen como errores
+        let (entries, errors) = discover_levels("levels");
+        for error in &errors {
+            println!("FALLA\n  - {}", error);
+            failed += 1;
+        }
+        paths = entries.into_iter().map(|entry| entry.path).collect();
+    }
+
+    for path in &paths {
+        match Level::load(path) {
+            Ok(level) => {
+                let report = validate(&level);
+                let route = report.route_length.map_or("sin ruta".to_string(), |d| format!("ruta más corta {} celdas", d));
+                println!("{}: {} ({})", path.display(), if report.is_ok() { "ok" } else { "FALLA" }, route);
+                for problem in &report.problems {
+                    println!("  - {}", problem);
+                }
+                if !report.is_ok() { failed += 1; }
+            }
+            Err(e) => {
+                println!("{}: FALLA\n  - {}", path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 { 1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetype::{Archetype, DEFAULT_SPEED};
+    use crate::level::EnemySpawn;
+    use crate::maze::{RaggedRows, parse_maze};
+
+    #[test]
+    fn shipped_levels_have_no_problems() {
+        for path in ["levels/easy.level", "levels/hard.level"] {
+            let level = Level::load(path).unwrap_or_else(|e| panic!("{}", e));
+            let report = validate(&level);
+            let problems: Vec<String> = report.problems.iter().map(|p| p.to_string()).collect();
+            assert!(report.is_ok(), "{}: {:?}", path, problems);
+        }
+    }
+
+    #[test]
+    fn enemy_inside_wall_is_blocked() {
+        let mut level = Level::load("levels/easy.level").unwrap();
+        level.enemies.push(EnemySpawn { pos: Vector2::new(0.5, 0.5), archetype: Archetype::reggie(DEFAULT_SPEED), waypoints: Vec::new() });
+        let report = validate(&level);
+        assert!(report.problems.iter().any(|p| matches!(p, Problem::EnemyBlocked { index: 0, .. })));
+    }
+
+    #[test]
+    fn maze_without_exit_is_reported() {
+        let mut level = Level::load("levels/easy.level").unwrap();
+        let (maze, _) = parse_maze("+--+--+\n|     |\n+--+--+", RaggedRows::Reject);
+        level.maze = maze;
+        level.start = Vector2::new(1.5, 1.5);
+        let report = validate(&level);
+        assert!(report.problems.iter().any(|p| matches!(p, Problem::NoExit)));
+        assert!(report.route_length.is_none());
+    }
+}